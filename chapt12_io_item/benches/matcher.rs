@@ -0,0 +1,75 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use chapt12_io_item::Matcher;
+
+// 约 16 MB 的英文文本，查询只出现在少数几行
+fn corpus() -> String {
+    let words = [
+        "the",
+        "quick",
+        "brown",
+        "fox",
+        "jumps",
+        "over",
+        "lazy",
+        "dog",
+        "Rust",
+        "safe",
+        "fast",
+        "productive",
+        "memory",
+        "thread",
+        "borrow",
+        "checker",
+    ];
+    let mut state: u32 = 42;
+    let mut text = String::with_capacity(16 << 20);
+    let mut line = 0;
+    while text.len() < 16 << 20 {
+        for _ in 0..12 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            text.push_str(words[(state >> 16) as usize % words.len()]);
+            text.push(' ');
+        }
+        if line % 1000 == 0 {
+            text.push_str("Sherlock Holmes");
+        }
+        text.push('\n');
+        line += 1;
+    }
+    text
+}
+
+fn literal(c: &mut Criterion) {
+    let text = corpus();
+    let mut group = c.benchmark_group("literal");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.sample_size(20);
+
+    for query in ["Holmes", "Sherlock Holmes", "z"] {
+        group.bench_function(format!("contains/{}", query), |b| {
+            b.iter(|| text.lines().filter(|line| line.contains(query)).count())
+        });
+        let matcher = Matcher::literal(query, false);
+        group.bench_function(format!("matcher/{}", query), |b| {
+            b.iter(|| text.lines().filter(|line| matcher.is_match(line)).count())
+        });
+
+        // 原来的实现：每一行都转换为小写
+        group.bench_function(format!("to_lowercase/{}", query), |b| {
+            b.iter(|| {
+                text.lines()
+                    .filter(|line| line.to_lowercase().contains(&query.to_lowercase()))
+                    .count()
+            })
+        });
+        let matcher = Matcher::literal(query, true);
+        group.bench_function(format!("matcher_ignore_case/{}", query), |b| {
+            b.iter(|| text.lines().filter(|line| matcher.is_match(line)).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, literal);
+criterion_main!(benches);
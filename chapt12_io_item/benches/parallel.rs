@@ -0,0 +1,64 @@
+use std::{env, fs, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use chapt12_io_item::{parallel::default_threads, search_files, Matcher, SearchOptions};
+
+// 生成 200 个文件，每个约 100 KB，返回目录和文件路径
+fn corpus() -> (PathBuf, Vec<String>) {
+    let dir = env::temp_dir().join(format!("chapt12_bench_parallel_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut paths = Vec::new();
+    for i in 0..200 {
+        let contents: String = (0..2000)
+            .map(|line| {
+                if line % 500 == 0 {
+                    format!("{} the quick brown fox finds a needle here\n", line)
+                } else {
+                    format!(
+                        "{} lorem ipsum dolor sit amet consectetur adipiscing\n",
+                        line
+                    )
+                }
+            })
+            .collect();
+        let path: PathBuf = dir.join(format!("file{:03}.txt", i));
+        fs::write(&path, contents).unwrap();
+        paths.push(path.display().to_string());
+    }
+    (dir, paths)
+}
+
+fn parallel_search(c: &mut Criterion) {
+    let (dir, paths) = corpus();
+    let matcher = Matcher::literal("needle", false);
+    let options = SearchOptions::default();
+
+    let mut group = c.benchmark_group("search_files");
+    // 核数和前面的线程数相同时 criterion 会因为重复的名称 panic
+    let mut counts = vec![1, 2, 4, default_threads()];
+    counts.sort_unstable();
+    counts.dedup();
+    for threads in counts {
+        group.bench_function(format!("threads={}", threads), |b| {
+            b.iter(|| {
+                let mut total = 0;
+                search_files(&matcher, &paths, &options, threads, |_, matches| {
+                    total += matches?.count;
+                    Ok(())
+                })
+                .unwrap();
+                assert_eq!(200 * 4, total);
+            })
+        });
+    }
+    group.finish();
+
+    // 测试文件大约 20 MB，不留在临时目录中
+    fs::remove_dir_all(dir).unwrap();
+}
+
+criterion_group!(benches, parallel_search);
+criterion_main!(benches);
@@ -0,0 +1,309 @@
+use std::{env, error::Error, fmt};
+
+use crate::{glob::Glob, printer::ColorChoice, reader::STDIN};
+
+pub const USAGE: &str = "\
+Usage: minigrep [OPTIONS] <QUERY> [PATH]...
+
+Search for lines containing QUERY in each PATH. Directories are searched
+recursively, skipping binary files and files ignored by .gitignore.
+With no PATH, or when PATH is -, read standard input.
+
+Options:
+  -i, --ignore-case         ignore case distinctions (also enabled by IGNORE_CASE)
+  -v, --invert-match        select non-matching lines
+  -n, --line-number         prefix each line with its line number
+  -c, --count               print only the number of selected lines
+  -w, --word                match only whole words
+      --regex               treat QUERY as a regular expression
+  -A, --after-context NUM   print NUM lines of trailing context
+  -B, --before-context NUM  print NUM lines of leading context
+  -C, --context NUM         print NUM lines of leading and trailing context
+      --color[=WHEN]        highlight matches; WHEN is always, never or auto
+      --json                print one JSON object per matching line
+      --replace TEXT        print matching lines with every match replaced by TEXT;
+                            with --regex, $1 or ${name} insert capture groups
+      --in-place[=SUFFIX]   with --replace, rewrite the files instead of printing,
+                            keeping a backup with SUFFIX appended if given
+      --dry-run             with --in-place, print a diff instead of writing
+  -j, --threads NUM         search files with NUM threads (default: CPU count)
+  -H, --with-filename       always prefix lines with the file name
+      --no-filename         never prefix lines with the file name
+      --include GLOB        search only files matching GLOB (repeatable)
+      --exclude GLOB        skip files and directories matching GLOB (repeatable)
+      --no-ignore           do not respect .gitignore files
+  -h, --help                print this help and exit
+  -V, --version             print version information and exit
+";
+
+// 命令行用法错误，main 中以退出码 2 结束
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    pub query: String,
+    pub paths: Vec<String>,
+    pub ignore_case: bool,
+    pub invert_match: bool,
+    pub line_number: bool,
+    pub count: bool,
+    pub word: bool,
+    pub regex: bool,
+    // None 时搜索多个文件或目录才加上文件名
+    pub with_filename: Option<bool>,
+    pub include: Vec<Glob>,
+    pub exclude: Vec<Glob>,
+    pub no_ignore: bool,
+    // -A 和 -B 优先于 -C
+    pub before_context: Option<usize>,
+    pub after_context: Option<usize>,
+    pub color: ColorChoice,
+    pub json: bool,
+    pub replace: Option<String>,
+    // --in-place 修改文件，backup_suffix 不为 None 时保留备份
+    pub in_place: bool,
+    pub backup_suffix: Option<String>,
+    pub dry_run: bool,
+    // None 时使用 CPU 核数
+    pub threads: Option<usize>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Args {
+    /// 解析命令行参数，第一个参数是程序名。
+    ///
+    /// 短选项可以合并（`-in`），带值的选项可以写成 `--include GLOB`、`--include=GLOB`、
+    /// `-A 2` 或 `-A2`，`--` 之后的参数都当作位置参数。
+    /// 没有指定 `-i` 时，设置了 `IGNORE_CASE` 环境变量也会忽略大小写。
+    ///
+    /// # Errors
+    ///
+    /// 未知选项、选项缺少值、glob 不合法或缺少查询时返回 `UsageError`。
+    pub fn build(args: impl Iterator<Item = String>) -> Result<Args, UsageError> {
+        let mut parsed = Args::default();
+        let mut positional = Vec::new();
+        let mut options_done = false;
+        // --color=auto 和默认值相同，单独记录是否给出过
+        let mut color_given = false;
+
+        let mut args = args.skip(1);
+        while let Some(arg) = args.next() {
+            if options_done || arg == "-" || !arg.starts_with('-') {
+                positional.push(arg);
+            } else if arg == "--" {
+                options_done = true;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                if takes_value(name) {
+                    let value = match value {
+                        Some(value) => value,
+                        None => args.next().ok_or_else(|| {
+                            UsageError(format!("option '--{}' requires a value", name))
+                        })?,
+                    };
+                    parsed.set_value(name, &value)?;
+                } else if name == "color" {
+                    // 和 grep 一样，单独的 --color 等于 --color=auto
+                    let value = value.as_deref().unwrap_or("auto");
+                    color_given = true;
+                    parsed.color = ColorChoice::parse(value).ok_or_else(|| {
+                        UsageError(format!(
+                            "invalid argument '{}' for '--color', expected always, never or auto",
+                            value
+                        ))
+                    })?;
+                } else if name == "in-place" {
+                    // 和 sed -i 一样，后缀只能用 = 连在选项后面
+                    parsed.in_place = true;
+                    parsed.backup_suffix = value.filter(|suffix| !suffix.is_empty());
+                } else if value.is_some() {
+                    return Err(UsageError(format!(
+                        "option '--{}' doesn't take a value",
+                        name
+                    )));
+                } else {
+                    parsed.set_long(name)?;
+                }
+            } else {
+                let flags = &arg[1..];
+                for (i, flag) in flags.char_indices() {
+                    let Some(name) = short_value_name(flag) else {
+                        parsed.set_short(flag)?;
+                        continue;
+                    };
+                    // 值可以紧跟在选项后面，也可以是下一个参数
+                    let rest = &flags[i + flag.len_utf8()..];
+                    let value = if rest.is_empty() {
+                        args.next().ok_or_else(|| {
+                            UsageError(format!("option '-{}' requires a value", flag))
+                        })?
+                    } else {
+                        rest.to_string()
+                    };
+                    parsed.set_value(name, &value)?;
+                    break;
+                }
+            }
+        }
+
+        // --help 和 --version 不需要其他参数
+        if parsed.help || parsed.version {
+            return Ok(parsed);
+        }
+
+        let mut positional = positional.into_iter();
+        parsed.query = positional
+            .next()
+            .ok_or_else(|| UsageError("Didn't get a query string".to_string()))?;
+        parsed.paths = positional.collect();
+        // 没有给出文件时读取标准输入
+        if parsed.paths.is_empty() {
+            parsed.paths.push(STDIN.to_string());
+        }
+
+        if parsed.in_place && parsed.replace.is_none() {
+            return Err(UsageError(
+                "option '--in-place' requires '--replace'".to_string(),
+            ));
+        }
+        if parsed.in_place && parsed.paths.iter().any(|path| path == STDIN) {
+            return Err(UsageError(
+                "option '--in-place' can't rewrite standard input".to_string(),
+            ));
+        }
+        // 这些选项只影响搜索结果的输出或搜索方式，和 --in-place 一起使用时没有意义
+        let conflicts = [
+            ("-v", parsed.invert_match),
+            ("-c", parsed.count),
+            (
+                "-A/-B/-C",
+                parsed.after_context.is_some() || parsed.before_context.is_some(),
+            ),
+            ("--json", parsed.json),
+            ("--color", color_given),
+            ("-n", parsed.line_number),
+            ("-H/--no-filename", parsed.with_filename.is_some()),
+            ("-j", parsed.threads.is_some()),
+        ];
+        if let (true, Some((name, _))) =
+            (parsed.in_place, conflicts.iter().find(|(_, given)| *given))
+        {
+            return Err(UsageError(format!(
+                "option '--in-place' can't be used with '{}'",
+                name
+            )));
+        }
+        if parsed.dry_run && !parsed.in_place {
+            return Err(UsageError(
+                "option '--dry-run' requires '--in-place'".to_string(),
+            ));
+        }
+
+        if !parsed.ignore_case {
+            parsed.ignore_case = env::var("IGNORE_CASE").is_ok();
+        }
+        Ok(parsed)
+    }
+
+    fn set_value(&mut self, name: &str, value: &str) -> Result<(), UsageError> {
+        let glob = || Glob::new(value).map_err(|e| UsageError(e.to_string()));
+        let lines = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| UsageError(format!("invalid context length '{}'", value)))
+        };
+        match name {
+            "include" => self.include.push(glob()?),
+            "exclude" => self.exclude.push(glob()?),
+            "after-context" => self.after_context = Some(lines()?),
+            "before-context" => self.before_context = Some(lines()?),
+            "replace" => self.replace = Some(value.to_string()),
+            "threads" => {
+                let threads = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|threads| *threads > 0)
+                    .ok_or_else(|| UsageError(format!("invalid number of threads '{}'", value)))?;
+                self.threads = Some(threads);
+            }
+            "context" => {
+                let lines = lines()?;
+                self.after_context.get_or_insert(lines);
+                self.before_context.get_or_insert(lines);
+            }
+            _ => unreachable!("--{} doesn't take a value", name),
+        }
+        Ok(())
+    }
+
+    fn set_long(&mut self, name: &str) -> Result<(), UsageError> {
+        match name {
+            "ignore-case" => self.ignore_case = true,
+            "invert-match" => self.invert_match = true,
+            "line-number" => self.line_number = true,
+            "count" => self.count = true,
+            "word" => self.word = true,
+            "regex" => self.regex = true,
+            "with-filename" => self.with_filename = Some(true),
+            "no-filename" => self.with_filename = Some(false),
+            "no-ignore" => self.no_ignore = true,
+            "json" => self.json = true,
+            "dry-run" => self.dry_run = true,
+            "help" => self.help = true,
+            "version" => self.version = true,
+            _ => return Err(UsageError(format!("unknown option '--{}'", name))),
+        }
+        Ok(())
+    }
+
+    fn set_short(&mut self, flag: char) -> Result<(), UsageError> {
+        match flag {
+            'i' => self.ignore_case = true,
+            'v' => self.invert_match = true,
+            'n' => self.line_number = true,
+            'c' => self.count = true,
+            'w' => self.word = true,
+            'H' => self.with_filename = Some(true),
+            'h' => self.help = true,
+            'V' => self.version = true,
+            _ => return Err(UsageError(format!("unknown option '-{}'", flag))),
+        }
+        Ok(())
+    }
+}
+
+fn takes_value(name: &str) -> bool {
+    matches!(
+        name,
+        "include"
+            | "exclude"
+            | "after-context"
+            | "before-context"
+            | "context"
+            | "threads"
+            | "replace"
+    )
+}
+
+fn short_value_name(flag: char) -> Option<&'static str> {
+    match flag {
+        'A' => Some("after-context"),
+        'B' => Some("before-context"),
+        'C' => Some("context"),
+        'j' => Some("threads"),
+        _ => None,
+    }
+}
@@ -0,0 +1,134 @@
+// 忽略大小写的字面量查询：按 Unicode 完整大小写折叠比较
+//
+// `to_lowercase` 不能让 ß 和 SS、ſ 和 s、ς 和 Σ 相互匹配，而且转换后字节长度可能改变，
+// 匹配的位置没法直接用在原始的行上。这里逐个字符折叠，同时记录每个字符在原始行中的位置，
+// 匹配的位置会映射回原始的行。土耳其语的 I/ı 和 İ/i 按 Unicode 的默认规则处理，
+// İ 折叠为 i 加上组合用的上点 U+0307。
+
+use std::ops::Range;
+
+use crate::finder::Finder;
+
+#[derive(Debug, Clone)]
+pub struct CaseFolded {
+    // 折叠后的查询
+    query: String,
+    // 查询折叠后只有 ASCII 字符时，ASCII 的行直接用 Horspool 查找
+    ascii: Option<Finder>,
+}
+
+impl CaseFolded {
+    pub fn new(query: &str) -> CaseFolded {
+        let query = fold(query);
+        let ascii = query
+            .is_ascii()
+            .then(|| Finder::ascii_case_insensitive(&query));
+        CaseFolded { query, ascii }
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        if line.is_ascii() {
+            // ASCII 的行折叠后仍然是 ASCII，不能匹配包含其他字符的查询
+            return self
+                .ascii
+                .as_ref()
+                .is_some_and(|ascii| ascii.is_match(line));
+        }
+        self.query.is_empty()
+            || FoldedLine::new(line)
+                .find_iter(&self.query)
+                .next()
+                .is_some()
+    }
+
+    /// 匹配在原始行中的字节范围，按位置排序且互不重叠，查询为空时没有结果。
+    pub fn find_iter(&self, line: &str) -> Vec<Range<usize>> {
+        if line.is_ascii() {
+            return match &self.ascii {
+                Some(ascii) => ascii.find_iter(line).collect(),
+                None => Vec::new(),
+            };
+        }
+        FoldedLine::new(line).find_iter(&self.query).collect()
+    }
+}
+
+/// 按 Unicode 完整大小写折叠转换 `s`，折叠后相同的字符串只有大小写不同。
+pub fn fold(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+    for c in s.chars() {
+        fold_char(c, &mut folded);
+    }
+    folded
+}
+
+fn fold_char(c: char, out: &mut String) {
+    if c.is_ascii() {
+        out.push(c.to_ascii_lowercase());
+        return;
+    }
+    // 无点的 ı 在默认规则中没有折叠，不能通过大写 I 和 i 相等
+    if c == 'ı' {
+        out.push(c);
+        return;
+    }
+    for lower in c.to_lowercase() {
+        let mut upper = lower.to_uppercase();
+        if upper.len() > 1 {
+            // ß、ﬁ 等大写后是多个字符，折叠为大写的小写形式，例如 ß → ss
+            for part in upper {
+                out.extend(part.to_lowercase());
+            }
+            continue;
+        }
+        // ſ、ς 等小写的变体折叠为大写对应的小写字母
+        let upper = upper.next().unwrap_or(lower);
+        let mut again = upper.to_lowercase();
+        match (again.len(), again.next()) {
+            (1, Some(folded)) => out.push(folded),
+            _ => out.push(lower),
+        }
+    }
+}
+
+// 折叠后的行
+struct FoldedLine {
+    text: String,
+    // 折叠后每个字节位置对应的原始位置，位于一个原始字符折叠结果的中间时为 None
+    origin: Vec<Option<usize>>,
+}
+
+impl FoldedLine {
+    fn new(line: &str) -> FoldedLine {
+        let mut text = String::with_capacity(line.len());
+        let mut origin = Vec::with_capacity(line.len() + 1);
+        for (i, c) in line.char_indices() {
+            origin.push(Some(i));
+            fold_char(c, &mut text);
+            origin.resize(text.len(), None);
+        }
+        origin.push(Some(line.len()));
+        FoldedLine { text, origin }
+    }
+
+    // 只返回开头和结尾都落在原始字符边界上的匹配，例如 ß 折叠为 ss 后不能只匹配其中一个 s
+    fn find_iter<'a>(&'a self, query: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            if query.is_empty() {
+                return None;
+            }
+            while let Some(found) = self.text.get(pos..).and_then(|rest| rest.find(query)) {
+                let start = pos + found;
+                let end = start + query.len();
+                if let (Some(from), Some(to)) = (self.origin[start], self.origin[end]) {
+                    pos = end;
+                    return Some(from..to);
+                }
+                // 从下一个字符继续查找，可能和这次的位置重叠
+                pos = start + self.text[start..].chars().next().map_or(1, char::len_utf8);
+            }
+            None
+        })
+    }
+}
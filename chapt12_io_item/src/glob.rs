@@ -0,0 +1,181 @@
+use std::{error::Error, fmt};
+
+// 通配符模式：`*` 和 `?` 不匹配 `/`，`**` 可以跨目录，`[a-z]`、`[!0-9]` 匹配字符集合
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    // ?
+    Any,
+    // *
+    Star,
+    // **
+    DoubleStar,
+    // **/，零个或多个目录
+    AnyDirs,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobError {
+    pub pattern: String,
+    pub message: String,
+}
+
+impl fmt::Display for GlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid glob {:?}: {}", self.pattern, self.message)
+    }
+}
+
+impl Error for GlobError {}
+
+impl Glob {
+    /// # Errors
+    ///
+    /// `[` 没有对应的 `]` 或者模式以单独的 `\` 结尾时返回错误。
+    pub fn new(pattern: &str) -> Result<Glob, GlobError> {
+        let error = |message: &str| GlobError {
+            pattern: pattern.to_string(),
+            message: message.to_string(),
+        };
+
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '?' => Token::Any,
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        Token::AnyDirs
+                    } else {
+                        Token::DoubleStar
+                    }
+                }
+                '*' => Token::Star,
+                '\\' => Token::Char(chars.next().ok_or_else(|| error("trailing '\\'"))?),
+                '[' => {
+                    let negated = matches!(chars.peek(), Some('!' | '^'));
+                    if negated {
+                        chars.next();
+                    }
+                    let mut ranges = Vec::new();
+                    // 紧跟在 [ 后面的 ] 是普通字符
+                    let mut first = true;
+                    loop {
+                        let start = match chars.next() {
+                            Some(']') if !first => break,
+                            Some(c) => c,
+                            None => return Err(error("unclosed character class")),
+                        };
+                        first = false;
+                        let mut lookahead = chars.clone();
+                        if lookahead.next() == Some('-')
+                            && lookahead.peek().is_some_and(|c| *c != ']')
+                        {
+                            chars.next();
+                            let end = chars.next().unwrap();
+                            ranges.push((start, end));
+                        } else {
+                            ranges.push((start, start));
+                        }
+                    }
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Char(c),
+            };
+            tokens.push(token);
+        }
+
+        Ok(Glob {
+            pattern: pattern.to_string(),
+            tokens,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 模式中是否包含 `/`，包含时需要和相对路径而不是文件名比较。
+    pub fn has_separator(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|token| matches!(token, Token::Char('/') | Token::AnyDirs))
+    }
+
+    /// `path` 使用 `/` 分隔目录。
+    pub fn is_match(&self, path: &str) -> bool {
+        let chars: Vec<char> = path.chars().collect();
+        match_tokens(&self.tokens, &chars)
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+// 回溯匹配，记录已经失败的（模式位置，路径位置），
+// 多个 * 时每个位置只尝试一次，不会出现指数级的回溯
+fn match_tokens(tokens: &[Token], path: &[char]) -> bool {
+    let mut failed = vec![false; (tokens.len() + 1) * (path.len() + 1)];
+    match_from(tokens, path, 0, 0, &mut failed)
+}
+
+fn match_from(tokens: &[Token], path: &[char], t: usize, p: usize, failed: &mut [bool]) -> bool {
+    let key = t * (path.len() + 1) + p;
+    if failed[key] {
+        return false;
+    }
+    let matched = match tokens.get(t) {
+        None => p == path.len(),
+        Some(Token::Star) => (p..=path.len())
+            .take_while(|&i| i == p || path[i - 1] != '/')
+            .any(|i| match_from(tokens, path, t + 1, i, failed)),
+        Some(Token::DoubleStar) => {
+            (p..=path.len()).any(|i| match_from(tokens, path, t + 1, i, failed))
+        }
+        // 零个目录，或者在某个 / 之后继续匹配
+        Some(Token::AnyDirs) => {
+            match_from(tokens, path, t + 1, p, failed)
+                || (p..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| match_from(tokens, path, t + 1, i + 1, failed))
+        }
+        Some(token) => {
+            p < path.len()
+                && matches_char(token, path[p])
+                && match_from(tokens, path, t + 1, p + 1, failed)
+        }
+    };
+    if !matched {
+        failed[key] = true;
+    }
+    matched
+}
+
+fn matches_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => *expected == c,
+        Token::Any => c != '/',
+        Token::Class { negated, ranges } => {
+            let found = ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&c));
+            c != '/' && found != *negated
+        }
+        Token::Star | Token::DoubleStar | Token::AnyDirs => false,
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::glob::Glob;
+
+// 一个目录中的 .gitignore 规则
+#[derive(Debug, Clone)]
+pub struct Gitignore {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    glob: Glob,
+    // ! 开头，重新包含被忽略的文件
+    negated: bool,
+    // / 结尾，只匹配目录
+    dir_only: bool,
+    // 包含 /，相对于 .gitignore 所在目录匹配；否则匹配任意层级的文件名
+    anchored: bool,
+}
+
+impl Gitignore {
+    /// 读取 `dir/.gitignore`，文件不存在时返回 `None`。
+    pub fn from_dir(dir: &Path) -> io::Result<Option<Gitignore>> {
+        match fs::read_to_string(dir.join(".gitignore")) {
+            Ok(contents) => Ok(Some(Gitignore::parse(dir, &contents))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 解析 .gitignore 的内容，无法解析的行会被忽略。
+    pub fn parse(dir: &Path, contents: &str) -> Gitignore {
+        let rules = contents.lines().filter_map(Rule::parse).collect();
+        Gitignore {
+            dir: dir.to_path_buf(),
+            rules,
+        }
+    }
+
+    /// `path` 在 `dir` 之下时，返回最后一条匹配的规则是否忽略它；没有规则匹配时返回 `None`。
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name = path.file_name()?.to_string_lossy();
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && if rule.anchored {
+                        rule.glob.is_match(&relative)
+                    } else {
+                        rule.glob.is_match(&name)
+                    }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        // 行尾没有转义的空格会被去掉
+        let line = line.trim_end_matches(['\r', ' ']);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let glob = Glob::new(line).ok()?;
+        Some(Rule {
+            glob,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
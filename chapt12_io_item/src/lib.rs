@@ -1,24 +1,273 @@
-use std::{env, error::Error, fs};
+use std::{
+    error::Error,
+    fs,
+    io::{self, BufRead, BufWriter, Write},
+    path::Path,
+};
+
+pub mod args;
+pub mod casefold;
+pub mod finder;
+pub mod glob;
+pub mod ignore;
+pub mod matcher;
+pub mod parallel;
+pub mod printer;
+pub mod reader;
+pub mod replace;
+pub mod searcher;
+pub mod walk;
+
+pub use args::{Args, UsageError};
+pub use finder::Finder;
+pub use matcher::Matcher;
+pub use parallel::{search_files, FileMatches};
+pub use printer::{ColorChoice, Printer};
+pub use reader::LineReader;
+pub use replace::{replace_file, Edit};
+pub use searcher::{search_context, search_matches, LineMatch, SearchOptions};
+pub use walk::Walker;
 
 pub fn run(args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
-    reult(args)
+    match reult(args) {
+        // 输出到 head 之类的命令时，管道提前关闭是正常的
+        Err(e) if e.downcast_ref::<io::Error>().is_some_and(is_broken_pipe) => Ok(()),
+        result => result,
+    }
+}
+
+fn is_broken_pipe(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::BrokenPipe
 }
 
 fn reult(args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
     let args = Args::build(args)?;
+    if args.help {
+        print!("{}", args::USAGE);
+        return Ok(());
+    }
+    if args.version {
+        println!("minigrep {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
 
-    let contents = fs::read_to_string(format!("./chapt12_io_item/{}", args.file_path))?;
-
-    let result = if args.ignore_case {
-        search_case_insensitive(&args.query, &contents)
+    let matcher = if args.word {
+        Matcher::word(&args.query, args.regex, args.ignore_case)?
+    } else if args.regex {
+        Matcher::regex(&args.query, args.ignore_case)?
     } else {
-        search(&args.query, &contents)
+        Matcher::literal(&args.query, args.ignore_case)
     };
 
-    for line in result {
-        println!("{}", line);
+    let mut walker = Walker::new().gitignore(!args.no_ignore);
+    for glob in &args.include {
+        walker = walker.include(glob.clone());
+    }
+    for glob in &args.exclude {
+        walker = walker.exclude(glob.clone());
+    }
+
+    // 和 grep 一样，搜索多个文件时才在每行前加上文件名
+    let with_filename = args.with_filename.unwrap_or_else(|| {
+        args.paths.len() > 1 || args.paths.iter().any(|path| Path::new(path).is_dir())
+    });
+
+    let options = SearchOptions {
+        invert_match: args.invert_match,
+        before_context: args.before_context.unwrap_or(0),
+        after_context: args.after_context.unwrap_or(0),
+    };
+
+    // 先列出所有要搜索的文件，目录中的文件读取失败时只给出警告
+    let mut inputs = Vec::new();
+    let mut failed = 0;
+    for path in &args.paths {
+        let input = Input {
+            path: path.clone(),
+            explicit: true,
+        };
+        let metadata = match path.as_str() {
+            reader::STDIN => None,
+            _ => Some(fs::metadata(path)),
+        };
+        let is_dir = match metadata {
+            None => false,
+            Some(Ok(metadata)) => metadata.is_dir(),
+            Some(Err(e)) => {
+                let e = io::Error::new(e.kind(), format!("{}: {}", path, e));
+                report(&input, Err(e), &mut failed)?;
+                continue;
+            }
+        };
+        if !is_dir {
+            inputs.push(input);
+            continue;
+        }
+        for file in walker.walk(Path::new(path)) {
+            match file {
+                Ok(file) => inputs.push(Input {
+                    path: file.display().to_string(),
+                    explicit: false,
+                }),
+                Err(e) => eprintln!("minigrep: {}", e),
+            }
+        }
+    }
+
+    if args.in_place {
+        edit_inputs(&matcher, &args, &inputs, &mut failed)?;
+        return check_failed(failed);
+    }
+
+    let stdout = io::stdout();
+    let mut out = Printer::new(BufWriter::new(stdout.lock()))
+        .with_filename(with_filename)
+        .line_number(args.line_number)
+        .separator(options.before_context > 0 || options.after_context > 0)
+        .color(!args.json && args.color.use_color())
+        .json(args.json)
+        .replace(args.replace.clone(), args.regex);
+
+    let threads = args.threads.unwrap_or_else(parallel::default_threads);
+    if threads == 1 || inputs.len() == 1 {
+        // 边读边输出，不需要在内存中保存结果
+        for input in &inputs {
+            let result = search_input(&matcher, &args, &options, &input.path, &mut out);
+            report(input, result, &mut failed)?;
+        }
+    } else {
+        // 多个线程同时搜索，按文件顺序输出
+        parallel::ordered_map(
+            &inputs,
+            threads,
+            |input| parallel::search_file(&matcher, &input.path, &options, args.count),
+            |input, result| {
+                let result = match result {
+                    // 轮到输出时还没有线程开始搜索，边读边输出
+                    None => search_input(&matcher, &args, &options, &input.path, &mut out),
+                    Some(result) => result.and_then(|matches| {
+                        if args.count {
+                            return out.count(&input.path, matches.count);
+                        }
+                        matches
+                            .lines
+                            .iter()
+                            .try_for_each(|line| out.line(&input.path, line, &matcher))
+                    }),
+                };
+                report(input, result, &mut failed)
+            },
+        )?;
+    }
+    out.flush()?;
+
+    check_failed(failed)
+}
+
+// 要搜索的文件，explicit 表示在命令行中直接给出
+struct Input {
+    path: String,
+    explicit: bool,
+}
+
+// 读取失败时给出警告后继续，命令行中给出的文件失败时计入 failed，最后以失败状态退出
+fn report(input: &Input, result: io::Result<()>, failed: &mut usize) -> io::Result<()> {
+    match result {
+        Err(e) if is_broken_pipe(&e) => Err(e),
+        Err(e) => {
+            eprintln!("minigrep: {}", e);
+            if input.explicit {
+                *failed += 1;
+            }
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+fn check_failed(failed: usize) -> Result<(), Box<dyn Error>> {
+    match failed {
+        0 => Ok(()),
+        1 => Err("1 file could not be read".into()),
+        n => Err(format!("{} files could not be read", n).into()),
+    }
+}
+
+// --in-place：把文件中的匹配替换后写回，--dry-run 时只输出差异
+fn edit_inputs(
+    matcher: &Matcher,
+    args: &Args,
+    inputs: &[Input],
+    failed: &mut usize,
+) -> io::Result<()> {
+    let replacement = args.replace.as_deref().unwrap_or_default();
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for input in inputs {
+        let result =
+            replace_file(matcher, &input.path, replacement, args.regex).and_then(
+                |edit| match edit {
+                    Some(edit) if args.dry_run => out.write_all(edit.diff().as_bytes()),
+                    Some(edit) => edit.write(args.backup_suffix.as_deref()),
+                    None => Ok(()),
+                },
+            );
+        report(input, result, failed)?;
+    }
+    out.flush()
+}
+
+// 搜索一个文件或标准输入并输出结果，二进制文件会被跳过
+fn search_input(
+    matcher: &Matcher,
+    args: &Args,
+    options: &SearchOptions,
+    path: &str,
+    out: &mut Printer<impl Write>,
+) -> io::Result<()> {
+    let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path, e));
+    let mut input = reader::open(path).map_err(with_path)?;
+    if reader::is_binary(&mut input).map_err(with_path)? {
+        return Ok(());
     }
 
+    if args.count {
+        let mut count = 0;
+        search_reader(matcher, input, options.invert_match, |_, _| {
+            count += 1;
+            Ok(())
+        })?;
+        return out.count(path, count);
+    }
+    search_context(matcher, input, options, |line| {
+        out.line(path, line, matcher)
+    })
+}
+
+/// 逐行读取 `reader`，对每个匹配的行（`invert_match` 时为不匹配的行）调用 `f`。
+///
+/// 每次只在内存中保留一行，可以搜索很大的文件；不是合法 UTF-8 的字节会替换为 U+FFFD。
+///
+/// # Errors
+///
+/// 读取失败或 `f` 返回错误时停止并返回该错误。
+pub fn search_reader<R, F>(
+    matcher: &Matcher,
+    reader: R,
+    invert_match: bool,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(usize, &str) -> io::Result<()>,
+{
+    let mut lines = LineReader::new(reader);
+    while let Some(line) = lines.next_line()? {
+        // -v 选择不匹配的行
+        if matcher.is_match(&line.text) != invert_match {
+            f(line.number, &line.text)?;
+        }
+    }
     Ok(())
 }
 
@@ -30,46 +279,20 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
     //     }
     // }
 
-    // 使用迭代器语法优化
-    contents
-        .lines()
-        .filter(|line| line.contains(query))
-        .collect()
+    // 查询只编译一次，每一行重复使用
+    Matcher::literal(query, false).search(contents)
 }
 
 // 大小写不敏感
 pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    contents
-        .lines()
-        .filter(|line| line.to_lowercase().contains(&query.to_lowercase()))
-        .collect()
-}
-
-struct Args {
-    query: String,
-    file_path: String,
-    ignore_case: bool,
+    Matcher::literal(query, true).search(contents)
 }
 
-impl Args {
-    fn build(mut args: impl Iterator<Item = String>) -> Result<Args, &'static str> {
-        args.next();
-
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
-        };
-        let file_path = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a file path"),
-        };
-
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
-
-        Ok(Args {
-            query,
-            file_path,
-            ignore_case,
-        })
-    }
+/// 使用正则表达式匹配每一行。
+///
+/// # Errors
+///
+/// `pattern` 不是合法的正则表达式时返回错误。
+pub fn search_regex<'a>(pattern: &str, contents: &'a str) -> Result<Vec<&'a str>, Box<dyn Error>> {
+    Ok(Matcher::regex(pattern, false)?.search(contents))
 }
@@ -1,5 +1,7 @@
 use std::{env, process};
 
+use chapt12_io_item::UsageError;
+
 fn main() {
     // let args: Vec<String> = env::args().collect();
     // run(&args).unwrap_or_else(|err| {
@@ -8,7 +10,14 @@ fn main() {
     // });
 
     if let Err(e) = chapt12_io_item::run(env::args()) {
-        eprintln!("Problen parsing arguments: {}", e);
+        // 用法错误和 grep 一样以 2 退出
+        if e.is::<UsageError>() {
+            eprintln!("minigrep: {}", e);
+            eprintln!("Usage: minigrep [OPTIONS] <QUERY> [PATH]...");
+            eprintln!("Try 'minigrep --help' for more information.");
+            process::exit(2);
+        }
+        eprintln!("minigrep: {}", e);
         process::exit(1);
     }
 }
@@ -0,0 +1,158 @@
+use std::{borrow::Cow, error::Error, ops::Range};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{casefold::CaseFolded, finder::Finder};
+
+// 预先编译好的查询，对每一行重复使用
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    // 区分大小写的字面量查询
+    Literal(Finder),
+    // 忽略大小写的字面量查询，按 Unicode 大小写折叠比较
+    CaseFolded(CaseFolded),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn literal(query: &str, ignore_case: bool) -> Matcher {
+        if ignore_case {
+            Matcher::CaseFolded(CaseFolded::new(query))
+        } else {
+            Matcher::Literal(Finder::new(query))
+        }
+    }
+
+    /// 正则表达式按行匹配，`^` 和 `$` 分别匹配行首和行尾。
+    ///
+    /// # Errors
+    ///
+    /// `pattern` 不是合法的正则表达式时返回错误，错误信息中包含出错的位置。
+    pub fn regex(pattern: &str, ignore_case: bool) -> Result<Matcher, Box<dyn Error>> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| format!("invalid regex {:?}: {}", pattern, e))?;
+        Ok(Matcher::Regex(regex))
+    }
+
+    /// 只匹配完整的单词，`query` 前后不能紧挨着字母、数字或下划线。
+    ///
+    /// # Errors
+    ///
+    /// `is_regex` 为 true 且 `query` 不是合法的正则表达式时返回错误。
+    pub fn word(query: &str, is_regex: bool, ignore_case: bool) -> Result<Matcher, Box<dyn Error>> {
+        let pattern = if is_regex {
+            format!(r"\b(?:{})\b", query)
+        } else {
+            // 以符号开头或结尾的查询在这一侧不需要单词边界
+            let is_word = |c: char| c.is_alphanumeric() || c == '_';
+            let before = if query.starts_with(is_word) {
+                r"\b"
+            } else {
+                ""
+            };
+            let after = if query.ends_with(is_word) { r"\b" } else { "" };
+            format!("{}{}{}", before, regex::escape(query), after)
+        };
+        Matcher::regex(&pattern, ignore_case)
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            Matcher::Literal(finder) => finder.is_match(line),
+            Matcher::CaseFolded(folded) => folded.is_match(line),
+            Matcher::Regex(regex) => regex.is_match(line),
+        }
+    }
+
+    /// 需要高亮的字节范围，按位置排序且互不重叠。
+    ///
+    /// 正则表达式中有捕获组时只高亮参与匹配的捕获组，
+    /// 例如 `key=(\w+)` 只高亮等号后面的值；没有捕获组时高亮整个匹配。
+    pub fn highlights(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Matcher::Literal(finder) => finder.find_iter(line).collect(),
+            Matcher::CaseFolded(folded) => folded.find_iter(line),
+            Matcher::Regex(regex) if regex.captures_len() > 1 => {
+                let mut spans: Vec<Range<usize>> = Vec::new();
+                for captures in regex.captures_iter(line) {
+                    for group in captures.iter().skip(1).flatten() {
+                        // 嵌套的捕获组合并到外层
+                        match spans.last_mut() {
+                            Some(last) if group.start() < last.end => {
+                                last.end = last.end.max(group.end());
+                            }
+                            _ if group.is_empty() => {}
+                            _ => spans.push(group.range()),
+                        }
+                    }
+                }
+                spans
+            }
+            Matcher::Regex(regex) => regex
+                .find_iter(line)
+                .filter(|m| !m.is_empty())
+                .map(|m| m.range())
+                .collect(),
+        }
+    }
+
+    /// 把 `line` 中所有的匹配替换为 `replacement`，返回替换后的行和替换进去的文本的字节范围。
+    ///
+    /// `expand` 为 true 时正则表达式中的 `$1`、`${name}` 展开为对应的捕获组，`$$` 表示 `$`；
+    /// 字面量查询没有捕获组，替换文本总是按原样插入。
+    pub fn replace(
+        &self,
+        line: &str,
+        replacement: &str,
+        expand: bool,
+    ) -> (String, Vec<Range<usize>>) {
+        // 每个匹配的位置和替换进去的文本
+        let insertions: Vec<(Range<usize>, Cow<str>)> = match self {
+            Matcher::Regex(regex) => regex
+                .captures_iter(line)
+                .map(|captures| {
+                    let range = captures.get(0).map_or(0..0, |m| m.range());
+                    if !expand {
+                        return (range, Cow::Borrowed(replacement));
+                    }
+                    let mut text = String::new();
+                    captures.expand(replacement, &mut text);
+                    (range, Cow::Owned(text))
+                })
+                .collect(),
+            Matcher::Literal(finder) => finder
+                .find_iter(line)
+                .map(|range| (range, Cow::Borrowed(replacement)))
+                .collect(),
+            Matcher::CaseFolded(folded) => folded
+                .find_iter(line)
+                .into_iter()
+                .map(|range| (range, Cow::Borrowed(replacement)))
+                .collect(),
+        };
+
+        let mut replaced = String::with_capacity(line.len());
+        let mut spans = Vec::new();
+        let mut last = 0;
+        for (range, text) in insertions {
+            replaced.push_str(&line[last..range.start]);
+            if !text.is_empty() {
+                spans.push(replaced.len()..replaced.len() + text.len());
+            }
+            replaced.push_str(&text);
+            last = range.end;
+        }
+        replaced.push_str(&line[last..]);
+        (replaced, spans)
+    }
+
+    /// `contents` 中匹配的行。
+    pub fn search<'a>(&self, contents: &'a str) -> Vec<&'a str> {
+        contents
+            .lines()
+            .filter(|line| self.is_match(line))
+            .collect()
+    }
+}
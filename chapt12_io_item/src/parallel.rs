@@ -0,0 +1,179 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{mpsc, Condvar, Mutex},
+    thread,
+};
+
+use crate::{
+    matcher::Matcher,
+    reader,
+    searcher::{search_context, LineMatch, SearchOptions},
+};
+
+// 一个文件的搜索结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileMatches {
+    // 匹配的行和上下文行，只统计数量时为空
+    pub lines: Vec<LineMatch>,
+    // 匹配的行数，不包括上下文
+    pub count: usize,
+}
+
+/// 默认的线程数：CPU 核数。
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// 在 `threads` 个线程中对 `items` 中的每一项调用 `work`，按 `items` 的顺序把结果交给 `emit`。
+///
+/// 轮到输出的一项还没有线程开始处理时，`emit` 收到 `None`，需要在当前线程中直接处理这一项，
+/// 例如边读边输出而不是缓存结果。其他线程最多比输出的位置提前 `threads * 2` 项，
+/// 缓存的结果数量有上限。输出顺序和线程数无关，`threads` 为 1 时每一项都直接处理。
+///
+/// # Errors
+///
+/// `emit` 返回错误时停止分发新的任务并返回该错误。
+pub fn ordered_map<I, T, W, E>(items: &[I], threads: usize, work: W, mut emit: E) -> io::Result<()>
+where
+    I: Sync,
+    T: Send,
+    W: Fn(&I) -> T + Sync,
+    E: FnMut(&I, Option<T>) -> io::Result<()>,
+{
+    let threads = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items.iter().try_for_each(|item| emit(item, None));
+    }
+
+    let window = threads * 2;
+    let claims = Mutex::new(Claims {
+        next: 0,
+        emitted: 0,
+        stop: false,
+    });
+    let changed = Condvar::new();
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::sync_channel(window);
+        // 当前线程也在处理，只需要再启动 threads - 1 个线程
+        for _ in 1..threads {
+            let sender = sender.clone();
+            let (claims, changed, work) = (&claims, &changed, &work);
+            scope.spawn(move || loop {
+                // 每次取下一项，大文件不会让其他线程闲着
+                let index = {
+                    let mut claims = claims.lock().unwrap();
+                    loop {
+                        if claims.stop || claims.next >= items.len() {
+                            return;
+                        }
+                        if claims.next < claims.emitted + window {
+                            break;
+                        }
+                        claims = changed.wait(claims).unwrap();
+                    }
+                    claims.next += 1;
+                    claims.next - 1
+                };
+                if sender.send((index, work(&items[index]))).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(sender);
+
+        // 还不能输出的结果，等前面的完成后再输出
+        let mut pending = BTreeMap::new();
+        for (index, item) in items.iter().enumerate() {
+            let result = loop {
+                if let Some(result) = pending.remove(&index) {
+                    break Some(result);
+                }
+                // 还没有线程开始处理，直接在当前线程中处理
+                let mut claims = claims.lock().unwrap();
+                if claims.next == index {
+                    claims.next += 1;
+                    break None;
+                }
+                drop(claims);
+                match receiver.recv() {
+                    Ok((done, result)) => pending.insert(done, result),
+                    // 工作线程 panic 了，离开 scope 时会继续 panic
+                    Err(_) => return Ok(()),
+                };
+            };
+
+            let emitted = emit(item, result);
+            let mut claims = claims.lock().unwrap();
+            claims.emitted = index + 1;
+            claims.stop = emitted.is_err();
+            changed.notify_all();
+            emitted?;
+        }
+        Ok(())
+    })
+}
+
+// ordered_map 中分发任务的状态
+struct Claims {
+    // 下一个要处理的位置
+    next: usize,
+    // 已经输出的项数
+    emitted: usize,
+    stop: bool,
+}
+
+/// 搜索一个文件，`path` 为 `-` 时读取标准输入，二进制文件返回空结果。
+///
+/// `count_only` 为 true 时只统计匹配的行数，不保存匹配的行。
+pub fn search_file(
+    matcher: &Matcher,
+    path: &str,
+    options: &SearchOptions,
+    count_only: bool,
+) -> io::Result<FileMatches> {
+    let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path, e));
+    let mut input = reader::open(path).map_err(with_path)?;
+    let mut matches = FileMatches::default();
+    if reader::is_binary(&mut input).map_err(with_path)? {
+        return Ok(matches);
+    }
+
+    search_context(matcher, input, options, |line| {
+        if !line.is_context {
+            matches.count += 1;
+        }
+        if !count_only {
+            matches.lines.push(line.clone());
+        }
+        Ok(())
+    })
+    .map_err(with_path)?;
+    Ok(matches)
+}
+
+/// 用 `threads` 个线程搜索 `paths`，按 `paths` 的顺序把每个文件的结果交给 `emit`。
+///
+/// # Errors
+///
+/// `emit` 返回错误时停止搜索并返回该错误，单个文件读取失败会作为结果交给 `emit`。
+pub fn search_files<E>(
+    matcher: &Matcher,
+    paths: &[String],
+    options: &SearchOptions,
+    threads: usize,
+    mut emit: E,
+) -> io::Result<()>
+where
+    E: FnMut(&str, io::Result<FileMatches>) -> io::Result<()>,
+{
+    ordered_map(
+        paths,
+        threads,
+        |path| search_file(matcher, path, options, false),
+        |path, result| {
+            let result = result.unwrap_or_else(|| search_file(matcher, path, options, false));
+            emit(path, result)
+        },
+    )
+}
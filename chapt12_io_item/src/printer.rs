@@ -0,0 +1,296 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    ops::Range,
+};
+
+use serde::Serialize;
+
+use crate::{matcher::Matcher, reader::STDIN, searcher::LineMatch};
+
+// 和 GNU grep 默认的 GREP_COLORS 一致
+const COLOR_MATCH: &str = "\x1b[1;31m";
+const COLOR_PATH: &str = "\x1b[35m";
+const COLOR_LINE_NUMBER: &str = "\x1b[32m";
+const COLOR_SEPARATOR: &str = "\x1b[36m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+// --color 的取值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    // 输出到终端时才使用颜色
+    #[default]
+    Auto,
+}
+
+impl ColorChoice {
+    pub fn parse(value: &str) -> Option<ColorChoice> {
+        match value {
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            "auto" => Some(ColorChoice::Auto),
+            _ => None,
+        }
+    }
+
+    /// `Auto` 时检查标准输出是否为终端，并遵守 `NO_COLOR` 和 `TERM=dumb`。
+    pub fn use_color(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                io::stdout().is_terminal()
+                    && env::var_os("NO_COLOR").is_none()
+                    && env::var("TERM").map_or(true, |term| term != "dumb")
+            }
+        }
+    }
+}
+
+// --json 输出的一行
+#[derive(Serialize)]
+struct JsonMatch<'a> {
+    file: &'a str,
+    line_number: usize,
+    // 行首在文件中的字节偏移
+    byte_offset: u64,
+    text: &'a str,
+    submatches: Vec<JsonSubmatch<'a>>,
+    // 使用 --replace 时替换后的行
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replacement: Option<String>,
+}
+
+// 匹配的位置是相对于行首、原始字节中的偏移，行中有无效的 UTF-8 时 text 中的字节已经替换为 U+FFFD
+#[derive(Serialize)]
+struct JsonSubmatch<'a> {
+    start: usize,
+    end: usize,
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonCount<'a> {
+    file: &'a str,
+    count: usize,
+}
+
+// 按 grep 的格式输出结果：匹配的行用 `:` 分隔前缀，上下文行用 `-`
+pub struct Printer<W> {
+    out: W,
+    with_filename: bool,
+    line_number: bool,
+    separator: bool,
+    color: bool,
+    json: bool,
+    // --replace 的替换文本，expand 表示展开其中的捕获组引用
+    replacement: Option<String>,
+    expand: bool,
+    // 上一次输出的文件和行号，用来判断是否需要输出 `--`
+    last: Option<(String, usize)>,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W) -> Printer<W> {
+        Printer {
+            out,
+            with_filename: false,
+            line_number: false,
+            separator: false,
+            color: false,
+            json: false,
+            replacement: None,
+            expand: false,
+            last: None,
+        }
+    }
+
+    pub fn with_filename(mut self, enabled: bool) -> Printer<W> {
+        self.with_filename = enabled;
+        self
+    }
+
+    pub fn line_number(mut self, enabled: bool) -> Printer<W> {
+        self.line_number = enabled;
+        self
+    }
+
+    /// 在不连续的行之间输出 `--`，显示上下文时使用。
+    pub fn separator(mut self, enabled: bool) -> Printer<W> {
+        self.separator = enabled;
+        self
+    }
+
+    /// 用 ANSI 转义序列高亮匹配的部分、文件名和行号。
+    pub fn color(mut self, enabled: bool) -> Printer<W> {
+        self.color = enabled;
+        self
+    }
+
+    /// 每个匹配的行输出一个 JSON 对象，上下文行会被忽略。
+    pub fn json(mut self, enabled: bool) -> Printer<W> {
+        self.json = enabled;
+        self
+    }
+
+    /// 输出匹配的行时把匹配替换为 `replacement`，上下文行保持不变。
+    ///
+    /// `expand` 为 true 时展开正则表达式的捕获组引用，见 [`Matcher::replace`]。
+    pub fn replace(mut self, replacement: Option<String>, expand: bool) -> Printer<W> {
+        self.replacement = replacement;
+        self.expand = expand;
+        self
+    }
+
+    pub fn line(&mut self, path: &str, line: &LineMatch, matcher: &Matcher) -> io::Result<()> {
+        if self.json {
+            return self.json_line(path, line, matcher);
+        }
+
+        if self.separator {
+            let contiguous = matches!(
+                &self.last,
+                Some((last_path, last_number))
+                    if last_path == path && last_number + 1 == line.line_number
+            );
+            if self.last.is_some() && !contiguous {
+                let separator = self.paint(COLOR_SEPARATOR, "--");
+                writeln!(self.out, "{}", separator)?;
+            }
+            self.last = Some((path.to_string(), line.line_number));
+        }
+
+        let delimiter = if line.is_context { "-" } else { ":" };
+        let delimiter = self.paint(COLOR_SEPARATOR, delimiter);
+        if self.with_filename {
+            let path = self.paint(COLOR_PATH, display_name(path));
+            write!(self.out, "{}{}", path, delimiter)?;
+        }
+        if self.line_number {
+            let number = self.paint(COLOR_LINE_NUMBER, &line.line_number.to_string());
+            write!(self.out, "{}{}", number, delimiter)?;
+        }
+
+        if line.is_context {
+            return writeln!(self.out, "{}", line.line);
+        }
+        match &self.replacement {
+            // 替换时高亮替换进去的文本
+            Some(replacement) => {
+                let (replaced, spans) = matcher.replace(&line.line, replacement, self.expand);
+                if self.color {
+                    writeln!(self.out, "{}", highlight(&replaced, &spans))
+                } else {
+                    writeln!(self.out, "{}", replaced)
+                }
+            }
+            None if self.color => {
+                let highlights = matcher.highlights(&line.line);
+                writeln!(self.out, "{}", highlight(&line.line, &highlights))
+            }
+            None => writeln!(self.out, "{}", line.line),
+        }
+    }
+
+    pub fn count(&mut self, path: &str, count: usize) -> io::Result<()> {
+        if self.json {
+            let file = display_name(path);
+            serde_json::to_writer(&mut self.out, &JsonCount { file, count })?;
+            return writeln!(self.out);
+        }
+        if self.with_filename {
+            let path = self.paint(COLOR_PATH, display_name(path));
+            let delimiter = self.paint(COLOR_SEPARATOR, ":");
+            write!(self.out, "{}{}", path, delimiter)?;
+        }
+        writeln!(self.out, "{}", count)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn json_line(&mut self, path: &str, line: &LineMatch, matcher: &Matcher) -> io::Result<()> {
+        if line.is_context {
+            return Ok(());
+        }
+        let submatches = matcher
+            .highlights(&line.line)
+            .into_iter()
+            .map(|range| JsonSubmatch {
+                start: raw_offset(line.raw.as_deref(), range.start),
+                end: raw_offset(line.raw.as_deref(), range.end),
+                text: &line.line[range],
+            })
+            .collect();
+        let json = JsonMatch {
+            file: display_name(path),
+            line_number: line.line_number,
+            byte_offset: line.byte_offset,
+            text: &line.line,
+            submatches,
+            replacement: self
+                .replacement
+                .as_ref()
+                .map(|replacement| matcher.replace(&line.line, replacement, self.expand).0),
+        };
+        serde_json::to_writer(&mut self.out, &json)?;
+        writeln!(self.out)
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, COLOR_RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// 用 ANSI 转义序列高亮 `line` 中的 `ranges`，范围需要按位置排序且互不重叠。
+pub fn highlight(line: &str, ranges: &[Range<usize>]) -> String {
+    let mut highlighted = String::with_capacity(line.len() + ranges.len() * 16);
+    let mut last = 0;
+    for range in ranges {
+        highlighted.push_str(&line[last..range.start]);
+        highlighted.push_str(COLOR_MATCH);
+        highlighted.push_str(&line[range.clone()]);
+        highlighted.push_str(COLOR_RESET);
+        last = range.end;
+    }
+    highlighted.push_str(&line[last..]);
+    highlighted
+}
+
+// 把 lossy 转换后的行中的位置映射为原始字节中的位置，
+// 每段无效的字节替换为一个 3 字节的 U+FFFD
+fn raw_offset(raw: Option<&[u8]>, pos: usize) -> usize {
+    let Some(raw) = raw else {
+        return pos;
+    };
+    let (mut lossy, mut original) = (0, 0);
+    for chunk in raw.utf8_chunks() {
+        let valid = chunk.valid().len();
+        if pos <= lossy + valid {
+            // 匹配总是落在字符边界上，不会在 U+FFFD 的中间
+            return original + pos.saturating_sub(lossy);
+        }
+        lossy += valid;
+        original += valid;
+        if !chunk.invalid().is_empty() {
+            lossy += char::REPLACEMENT_CHARACTER.len_utf8();
+            original += chunk.invalid().len();
+        }
+    }
+    original
+}
+
+fn display_name(path: &str) -> &str {
+    if path == STDIN {
+        "(standard input)"
+    } else {
+        path
+    }
+}
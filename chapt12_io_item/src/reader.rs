@@ -0,0 +1,81 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+// 读取缓冲区的大小，同时也是判断二进制文件时检查的字节数
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// 命令行中表示标准输入的路径。
+pub const STDIN: &str = "-";
+
+// 读取到的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line<'a> {
+    // 从 1 开始的行号
+    pub number: usize,
+    // 行首在输入中的字节偏移
+    pub offset: u64,
+    pub text: Cow<'a, str>,
+    // 去掉行尾换行符后的原始字节，text 中无效的字节已经替换为 U+FFFD
+    pub raw: &'a [u8],
+}
+
+// 逐行读取输入，每次只在内存中保留一行
+pub struct LineReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    line_number: usize,
+    offset: u64,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> LineReader<R> {
+        LineReader {
+            reader,
+            buf: Vec::new(),
+            line_number: 0,
+            offset: 0,
+        }
+    }
+
+    /// 返回下一行，行尾的 `\n` 或 `\r\n` 会被去掉。
+    ///
+    /// 不是合法 UTF-8 的字节会替换为 U+FFFD，合法的行不会复制。
+    pub fn next_line(&mut self) -> io::Result<Option<Line<'_>>> {
+        self.buf.clear();
+        let len = self.reader.read_until(b'\n', &mut self.buf)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        self.line_number += 1;
+        let offset = self.offset;
+        self.offset += len as u64;
+
+        let mut line = self.buf.as_slice();
+        if let Some(rest) = line.strip_suffix(b"\n") {
+            line = rest.strip_suffix(b"\r").unwrap_or(rest);
+        }
+        Ok(Some(Line {
+            number: self.line_number,
+            offset,
+            text: String::from_utf8_lossy(line),
+            raw: line,
+        }))
+    }
+}
+
+/// 缓冲区中还没有读取的内容包含 NUL 字节时认为是二进制数据，应该在读取之前调用。
+pub fn is_binary(reader: &mut impl BufRead) -> io::Result<bool> {
+    Ok(reader.fill_buf()?.contains(&0))
+}
+
+/// 打开文件，`path` 为 `-` 时读取标准输入。
+pub fn open(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == STDIN {
+        return Ok(Box::new(BufReader::with_capacity(BUFFER_SIZE, io::stdin())));
+    }
+    let file = File::open(path)?;
+    Ok(Box::new(BufReader::with_capacity(BUFFER_SIZE, file)))
+}
@@ -0,0 +1,176 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::matcher::Matcher;
+
+// 差异中每处修改前后保留的行数
+const DIFF_CONTEXT: usize = 3;
+
+// 替换一个文件中所有匹配的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub path: String,
+    // 原来的每一行，包括行尾的换行符
+    pub before: Vec<String>,
+    // 替换后对应的每一行，替换文本中有换行符时一项可能包含多行
+    pub after: Vec<String>,
+}
+
+/// 替换文件中每一行的所有匹配，没有匹配时返回 `None`。
+///
+/// 行尾的 `\n` 或 `\r\n` 保持不变。
+///
+/// # Errors
+///
+/// 读取失败，或文件包含 NUL 字节或不是合法的 UTF-8 时返回错误，这样的文件不会被修改。
+pub fn replace_file(
+    matcher: &Matcher,
+    path: &str,
+    replacement: &str,
+    expand: bool,
+) -> io::Result<Option<Edit>> {
+    let invalid = |reason: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}, not rewritten", path, reason),
+        )
+    };
+    let contents =
+        fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+    if contents.contains(&0) {
+        return Err(invalid("binary file"));
+    }
+    let contents = String::from_utf8(contents).map_err(|_| invalid("invalid UTF-8"))?;
+
+    let mut changed = false;
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    for line in contents.split_inclusive('\n') {
+        let text = line.strip_suffix('\n').unwrap_or(line);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let replaced = if matcher.is_match(text) {
+            let (replaced, _) = matcher.replace(text, replacement, expand);
+            changed |= replaced != text;
+            format!("{}{}", replaced, &line[text.len()..])
+        } else {
+            line.to_string()
+        };
+        before.push(line.to_string());
+        after.push(replaced);
+    }
+
+    Ok(changed.then(|| Edit {
+        path: path.to_string(),
+        before,
+        after,
+    }))
+}
+
+impl Edit {
+    /// 统一格式（unified diff）的差异，每处修改前后保留 3 行上下文。
+    pub fn diff(&self) -> String {
+        let changed: Vec<usize> = (0..self.before.len())
+            .filter(|&i| self.before[i] != self.after[i])
+            .collect();
+        // 替换后每一行之前有多少行，替换文本中有换行符时新旧行号不再相同
+        let mut new_start = vec![0];
+        let mut total = 0;
+        for line in &self.after {
+            total += line_count(line);
+            new_start.push(total);
+        }
+
+        let mut diff = format!("--- {}\n+++ {}\n", self.path, self.path);
+        let mut i = 0;
+        while i < changed.len() {
+            // 相邻修改之间的行不超过两倍上下文时合并为一段
+            let mut j = i;
+            while j + 1 < changed.len() && changed[j + 1] - changed[j] <= 2 * DIFF_CONTEXT + 1 {
+                j += 1;
+            }
+            let start = changed[i].saturating_sub(DIFF_CONTEXT);
+            let end = (changed[j] + DIFF_CONTEXT + 1).min(self.before.len());
+            let _ = writeln!(
+                diff,
+                "@@ -{},{} +{},{} @@",
+                start + 1,
+                end - start,
+                new_start[start] + 1,
+                new_start[end] - new_start[start]
+            );
+            for k in start..end {
+                if self.before[k] == self.after[k] {
+                    push_line(&mut diff, ' ', &self.before[k]);
+                    continue;
+                }
+                push_line(&mut diff, '-', &self.before[k]);
+                for line in self.after[k].split_inclusive('\n') {
+                    push_line(&mut diff, '+', line);
+                }
+            }
+            i = j + 1;
+        }
+        diff
+    }
+
+    /// 把替换后的内容写回文件。
+    ///
+    /// 先写入同一目录中的临时文件再重命名，写入过程中失败时原文件保持不变。
+    /// `backup_suffix` 不为 `None` 时，先把原文件复制到加上后缀的路径。
+    /// 符号链接会被解析，修改的是链接指向的文件，链接本身保持不变。
+    ///
+    /// # Errors
+    ///
+    /// 解析路径、创建、写入临时文件、备份或重命名失败时返回错误，并删除临时文件。
+    pub fn write(&self, backup_suffix: Option<&str>) -> io::Result<()> {
+        let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", self.path, e));
+        // 重命名到符号链接上会替换链接本身
+        let path = fs::canonicalize(&self.path).map_err(with_path)?;
+        let temp = temp_path(&path);
+        let result = self.write_to(&path, &temp, backup_suffix);
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result.map_err(with_path)
+    }
+
+    fn write_to(&self, path: &Path, temp: &Path, backup_suffix: Option<&str>) -> io::Result<()> {
+        let permissions = fs::metadata(path)?.permissions();
+        let mut file = OpenOptions::new().write(true).create_new(true).open(temp)?;
+        for line in &self.after {
+            file.write_all(line.as_bytes())?;
+        }
+        file.sync_all()?;
+        fs::set_permissions(temp, permissions)?;
+
+        if let Some(suffix) = backup_suffix {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(suffix);
+            fs::copy(path, backup)?;
+        }
+        fs::rename(temp, path)
+    }
+}
+
+// 和原文件在同一目录中，保证重命名不会跨文件系统
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.minigrep-{}.tmp", name, process::id()))
+}
+
+fn line_count(text: &str) -> usize {
+    text.split_inclusive('\n').count()
+}
+
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
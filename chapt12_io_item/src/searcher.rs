@@ -0,0 +1,101 @@
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    io::{self, BufRead},
+};
+
+use crate::{
+    matcher::Matcher,
+    reader::{Line, LineReader},
+};
+
+// 搜索选项，before 和 after 是 -B 和 -A 的上下文行数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub invert_match: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+}
+
+// 输出的一行：匹配的行或者它周围的上下文
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    pub line_number: usize,
+    // 行首在文件中的字节偏移
+    pub byte_offset: u64,
+    pub line: String,
+    // 行中有无效的 UTF-8 时保留原始字节，`line` 中的位置和原始字节中的位置不同
+    pub raw: Option<Vec<u8>>,
+    pub is_context: bool,
+}
+
+impl LineMatch {
+    fn new(line: &Line, is_context: bool) -> LineMatch {
+        LineMatch {
+            line_number: line.number,
+            byte_offset: line.offset,
+            line: line.text.to_string(),
+            raw: matches!(line.text, Cow::Owned(_)).then(|| line.raw.to_vec()),
+            is_context,
+        }
+    }
+}
+
+/// 逐行搜索 `reader`，按顺序对每个匹配的行和上下文行调用 `f`。
+///
+/// 相邻匹配的上下文重叠时每行只出现一次；两行的行号不连续时，
+/// 调用方可以在它们之间输出 `--` 分隔符。
+///
+/// # Errors
+///
+/// 读取失败或 `f` 返回错误时停止并返回该错误。
+pub fn search_context<R, F>(
+    matcher: &Matcher,
+    reader: R,
+    options: &SearchOptions,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(&LineMatch) -> io::Result<()>,
+{
+    let mut lines = LineReader::new(reader);
+    // 最近几行不匹配的行，遇到匹配时作为前面的上下文输出
+    let mut before: VecDeque<LineMatch> = VecDeque::with_capacity(options.before_context);
+    // 还需要输出几行后面的上下文
+    let mut after = 0;
+
+    while let Some(line) = lines.next_line()? {
+        if matcher.is_match(&line.text) != options.invert_match {
+            for context in before.drain(..) {
+                f(&context)?;
+            }
+            f(&LineMatch::new(&line, false))?;
+            after = options.after_context;
+        } else if after > 0 {
+            f(&LineMatch::new(&line, true))?;
+            after -= 1;
+        } else if options.before_context > 0 {
+            if before.len() == options.before_context {
+                before.pop_front();
+            }
+            before.push_back(LineMatch::new(&line, true));
+        }
+    }
+    Ok(())
+}
+
+/// 搜索 `contents`，返回匹配的行和上下文行。
+pub fn search_matches(
+    matcher: &Matcher,
+    contents: &str,
+    options: &SearchOptions,
+) -> Vec<LineMatch> {
+    let mut matches = Vec::new();
+    search_context(matcher, contents.as_bytes(), options, |line| {
+        matches.push(line.clone());
+        Ok(())
+    })
+    .expect("reading from memory never fails");
+    matches
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{glob::Glob, ignore::Gitignore};
+
+// 递归列出目录中需要搜索的文件
+#[derive(Debug, Clone)]
+pub struct Walker {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    gitignore: bool,
+}
+
+impl Default for Walker {
+    fn default() -> Walker {
+        Walker {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            gitignore: true,
+        }
+    }
+}
+
+impl Walker {
+    pub fn new() -> Walker {
+        Walker::default()
+    }
+
+    /// 只搜索匹配 `glob` 的文件，多次调用时匹配任意一个即可。
+    ///
+    /// 不含 `/` 的模式和文件名比较，否则和相对于搜索目录的路径比较。
+    pub fn include(mut self, glob: Glob) -> Walker {
+        self.include.push(glob);
+        self
+    }
+
+    /// 跳过匹配 `glob` 的文件和目录。
+    pub fn exclude(mut self, glob: Glob) -> Walker {
+        self.exclude.push(glob);
+        self
+    }
+
+    /// 是否遵守目录中的 `.gitignore`，默认遵守。
+    pub fn gitignore(mut self, enabled: bool) -> Walker {
+        self.gitignore = enabled;
+        self
+    }
+
+    /// 按文件名排序列出 `root` 下的文件，跳过 `.git` 目录。
+    ///
+    /// 读取某个目录失败不会中断遍历，错误和文件一起按顺序返回。
+    pub fn walk(&self, root: &Path) -> Vec<io::Result<PathBuf>> {
+        let mut files = Vec::new();
+        let mut ignores = Vec::new();
+        self.walk_dir(root, root, &mut ignores, &mut files);
+        files
+    }
+
+    fn walk_dir(
+        &self,
+        root: &Path,
+        dir: &Path,
+        ignores: &mut Vec<Gitignore>,
+        files: &mut Vec<io::Result<PathBuf>>,
+    ) {
+        let pushed = if self.gitignore {
+            match Gitignore::from_dir(dir) {
+                Ok(Some(gitignore)) => {
+                    ignores.push(gitignore);
+                    true
+                }
+                Ok(None) => false,
+                Err(e) => {
+                    files.push(Err(with_path(dir, e)));
+                    false
+                }
+            }
+        } else {
+            false
+        };
+
+        let mut entries =
+            match fs::read_dir(dir).and_then(|entries| entries.collect::<io::Result<Vec<_>>>()) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    files.push(Err(with_path(dir, e)));
+                    if pushed {
+                        ignores.pop();
+                    }
+                    return;
+                }
+            };
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            // 不跟随指向目录的符号链接，避免循环
+            let is_dir = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => false,
+                Ok(file_type) => file_type.is_dir(),
+                Err(e) => {
+                    files.push(Err(with_path(&path, e)));
+                    continue;
+                }
+            };
+            if is_dir && entry.file_name() == ".git" {
+                continue;
+            }
+            if is_ignored(ignores, &path, is_dir) {
+                continue;
+            }
+
+            let relative = relative_path(root, &path);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let matches = |glob: &Glob| {
+                glob.is_match(if glob.has_separator() {
+                    &relative
+                } else {
+                    &name
+                })
+            };
+            if self.exclude.iter().any(matches) {
+                continue;
+            }
+
+            if is_dir {
+                self.walk_dir(root, &path, ignores, files);
+                continue;
+            }
+            if !self.include.is_empty() && !self.include.iter().any(matches) {
+                continue;
+            }
+            files.push(Ok(path));
+        }
+
+        if pushed {
+            ignores.pop();
+        }
+    }
+}
+
+// 越深的 .gitignore 优先级越高
+fn is_ignored(ignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|gitignore| gitignore.matched(path, is_dir))
+        .unwrap_or(false)
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
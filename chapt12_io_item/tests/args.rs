@@ -0,0 +1,145 @@
+use chapt12_io_item::{Args, UsageError};
+
+fn parse(args: &[&str]) -> Result<Args, UsageError> {
+    Args::build(
+        std::iter::once("minigrep")
+            .chain(args.iter().copied())
+            .map(String::from),
+    )
+}
+
+#[test]
+fn parses_flags_and_positionals() {
+    let args = parse(&["-vn", "--count", "to", "poem.txt", "--word"]).unwrap();
+    assert_eq!("to", args.query);
+    assert_eq!(vec!["poem.txt"], args.paths);
+    assert!(args.invert_match && args.line_number && args.count && args.word);
+    assert!(!args.regex);
+
+    // -- 之后以 - 开头的参数也是查询
+    let args = parse(&["-i", "--", "-n", "poem.txt"]).unwrap();
+    assert_eq!("-n", args.query);
+    assert!(args.ignore_case && !args.line_number);
+
+    let args = parse(&[
+        "--include",
+        "*.rs",
+        "--exclude=target",
+        "fn",
+        "src",
+        "tests",
+    ])
+    .unwrap();
+    assert_eq!(vec!["src", "tests"], args.paths);
+    assert_eq!("*.rs", args.include[0].as_str());
+    assert_eq!("target", args.exclude[0].as_str());
+
+    let args = parse(&["-nC2", "-A", "1", "to"]).unwrap();
+    assert_eq!(
+        (Some(2), Some(1)),
+        (args.before_context, args.after_context)
+    );
+    assert!(args.line_number);
+
+    assert_eq!(Some(4), parse(&["-j4", "to"]).unwrap().threads);
+    assert_eq!(Some(2), parse(&["--threads=2", "to"]).unwrap().threads);
+    assert_eq!(None, parse(&["to"]).unwrap().threads);
+
+    let args = parse(&[
+        "--replace",
+        "$1",
+        "--in-place=.bak",
+        "--dry-run",
+        "to",
+        "poem.txt",
+    ])
+    .unwrap();
+    assert_eq!(Some("$1".to_string()), args.replace);
+    assert!(args.in_place && args.dry_run);
+    assert_eq!(Some(".bak".to_string()), args.backup_suffix);
+    let args = parse(&["--replace=", "--in-place", "to", "poem.txt"]).unwrap();
+    assert_eq!(
+        (Some(String::new()), None),
+        (args.replace, args.backup_suffix)
+    );
+
+    // 没有文件时读取标准输入
+    assert_eq!(vec!["-"], parse(&["to"]).unwrap().paths);
+
+    assert!(parse(&["--help"]).unwrap().help);
+    assert!(parse(&["-V"]).unwrap().version);
+}
+
+#[test]
+fn reports_usage_errors() {
+    assert_eq!(
+        Err(UsageError("unknown option '-x'".to_string())),
+        parse(&["-ix", "to", "poem.txt"])
+    );
+    assert_eq!(
+        Err(UsageError("unknown option '--colour'".to_string())),
+        parse(&["--colour", "to", "poem.txt"])
+    );
+    assert_eq!(
+        Err(UsageError("Didn't get a query string".to_string())),
+        parse(&["-n"])
+    );
+    assert_eq!(
+        Err(UsageError(
+            "option '--include' requires a value".to_string()
+        )),
+        parse(&["to", "poem.txt", "--include"])
+    );
+    assert_eq!(
+        Err(UsageError("invalid context length 'x'".to_string())),
+        parse(&["-Ax", "to"])
+    );
+    assert_eq!(
+        Err(UsageError(
+            "option '--in-place' requires '--replace'".to_string()
+        )),
+        parse(&["--in-place", "to", "poem.txt"])
+    );
+    assert_eq!(
+        Err(UsageError(
+            "option '--in-place' can't rewrite standard input".to_string()
+        )),
+        parse(&["--replace", "x", "--in-place", "to"])
+    );
+    for (flag, name) in [
+        ("-v", "-v"),
+        ("-c", "-c"),
+        ("-C1", "-A/-B/-C"),
+        ("-B2", "-A/-B/-C"),
+        ("--json", "--json"),
+        ("--color=auto", "--color"),
+        ("-n", "-n"),
+        ("-H", "-H/--no-filename"),
+        ("--no-filename", "-H/--no-filename"),
+        ("-j2", "-j"),
+    ] {
+        assert_eq!(
+            Err(UsageError(format!(
+                "option '--in-place' can't be used with '{}'",
+                name
+            ))),
+            parse(&["--replace=x", "--in-place", flag, "to", "poem.txt"])
+        );
+    }
+    assert_eq!(
+        Err(UsageError(
+            "option '--dry-run' requires '--in-place'".to_string()
+        )),
+        parse(&["--replace=x", "--dry-run", "to", "poem.txt"])
+    );
+    assert_eq!(
+        Err(UsageError("invalid number of threads '0'".to_string())),
+        parse(&["-j", "0", "to"])
+    );
+    assert_eq!(
+        Err(UsageError(
+            "invalid glob \"[a-\": unclosed character class".to_string()
+        )),
+        parse(&["--exclude=[a-", "to", "poem.txt"])
+    );
+}
@@ -0,0 +1,86 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn minigrep(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env_remove("IGNORE_CASE")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn prints_selected_lines() {
+    let output = minigrep(&["-inw", "to", "poem.txt"]);
+    assert!(output.status.success());
+    assert_eq!(
+        "6:How dreary to be somebody!\n8:To tell your name the livelong day\n9:To an admiring bog!\n",
+        stdout(&output)
+    );
+
+    let output = minigrep(&["-c", "-v", "o", "poem.txt"]);
+    assert_eq!("1\n", stdout(&output));
+
+    // 没有 -i 时使用环境变量
+    let output = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(["-c", "TO", "poem.txt"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("IGNORE_CASE", "1")
+        .output()
+        .unwrap();
+    assert_eq!("4\n", stdout(&output));
+}
+
+#[test]
+fn exits_with_2_on_bad_usage() {
+    let output = minigrep(&["--bogus", "to", "poem.txt"]);
+    assert_eq!(Some(2), output.status.code());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown option '--bogus'"));
+
+    assert_eq!(Some(2), minigrep(&["-n"]).status.code());
+
+    let output = minigrep(&["--help"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("Usage: minigrep"));
+}
+
+#[test]
+fn continues_after_missing_path() {
+    let output = minigrep(&["-c", "to", "missing.txt", "poem.txt"]);
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!("poem.txt:2\n", stdout(&output));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.txt: "));
+    assert!(stderr.contains("1 file could not be read"));
+}
+
+#[test]
+fn reads_stdin_lossily() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(["-n", "caf", "-", "poem.txt"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"tea\r\ncaf\xe9 latin-1\r\ncaf\xc3\xa9 utf-8\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        "(standard input):2:caf\u{fffd} latin-1\n(standard input):3:caf\u{e9} utf-8\n",
+        stdout(&output)
+    );
+}
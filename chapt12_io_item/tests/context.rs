@@ -0,0 +1,100 @@
+use std::process::Command;
+
+use chapt12_io_item::{search_matches, LineMatch, Matcher, SearchOptions};
+
+const CONTENTS: &str = "\
+one
+two match
+three
+four
+five
+six match
+seven match
+eight
+nine
+ten
+eleven match";
+
+fn summary(matches: &[LineMatch]) -> Vec<(usize, bool)> {
+    matches
+        .iter()
+        .map(|m| (m.line_number, m.is_context))
+        .collect()
+}
+
+#[test]
+fn merges_overlapping_context() {
+    let options = SearchOptions {
+        before_context: 1,
+        after_context: 1,
+        ..SearchOptions::default()
+    };
+    let matches = search_matches(&Matcher::literal("match", false), CONTENTS, &options);
+    assert_eq!(
+        vec![
+            (1, true),
+            (2, false),
+            (3, true),
+            (5, true),
+            (6, false),
+            (7, false),
+            (8, true),
+            (10, true),
+            (11, false),
+        ],
+        summary(&matches)
+    );
+    assert_eq!("two match", matches[1].line);
+
+    // 上下文窗口足够大时合并为一组
+    let options = SearchOptions {
+        before_context: 2,
+        after_context: 2,
+        ..SearchOptions::default()
+    };
+    let matches = search_matches(&Matcher::literal("match", false), CONTENTS, &options);
+    assert_eq!(11, matches.len());
+}
+
+#[test]
+fn context_with_inverted_match() {
+    let options = SearchOptions {
+        invert_match: true,
+        after_context: 1,
+        ..SearchOptions::default()
+    };
+    let matches = search_matches(&Matcher::literal("e", false), CONTENTS, &options);
+    assert_eq!(
+        vec![
+            (2, false),
+            (3, true),
+            (4, false),
+            (5, true),
+            (6, false),
+            (7, true)
+        ],
+        summary(&matches)
+    );
+}
+
+#[test]
+fn prints_group_separators() {
+    let output = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(["-n", "-B1", "-A", "1", "tell", "poem.txt", "-"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        "\
+poem.txt-2-Are you nobody, too?
+poem.txt:3:Then there's a pair of us - don't tell!
+poem.txt-4-They'd banish us, you know.
+--
+poem.txt-7-How public, like a frog
+poem.txt:8:To tell your name the livelong day
+poem.txt-9-To an admiring bog!
+",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
@@ -0,0 +1,79 @@
+use chapt12_io_item::{Finder, Matcher};
+
+// 简单的线性同余生成器，生成只包含少数几个字母的文本，让部分匹配经常出现
+fn corpus(len: usize, alphabet: &[u8]) -> String {
+    let mut state: u32 = 12345;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            alphabet[(state >> 16) as usize % alphabet.len()] as char
+        })
+        .collect()
+}
+
+fn naive(haystack: &str, needle: &str) -> Option<usize> {
+    let needle = needle.as_bytes();
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+}
+
+#[test]
+fn agrees_with_naive_search() {
+    let haystack = corpus(20_000, b"abcAbCa");
+    for needle in [
+        "a",
+        "B",
+        "ab",
+        "abc",
+        "CAB",
+        "bcabca",
+        "aaaaaa",
+        "abcabcabcab",
+        "d",
+    ] {
+        let finder = Finder::ascii_case_insensitive(needle);
+        for start in (0..haystack.len()).step_by(97) {
+            assert_eq!(
+                naive(&haystack[start..], needle),
+                finder.find(&haystack[start..]),
+                "needle {:?} at {}",
+                needle,
+                start
+            );
+        }
+    }
+}
+
+#[test]
+fn folds_ascii_case() {
+    let finder = Finder::ascii_case_insensitive("RuSt");
+    assert_eq!(Some(1), finder.find("Trust me"));
+    assert_eq!(Some(0), finder.find("RUST"));
+    assert_eq!(None, finder.find("rus t"));
+    assert_eq!(None, Finder::new("RuSt").find("Trust me"));
+
+    // 非 ASCII 字节按原样比较
+    let finder = Finder::ascii_case_insensitive("café");
+    assert_eq!(Some(3), finder.find("Le CAFé"));
+    assert_eq!(None, finder.find("LE CAFÉ"));
+}
+
+#[test]
+fn finds_non_overlapping_matches() {
+    let finder = Finder::ascii_case_insensitive("aa");
+    assert_eq!(
+        vec![0..2, 2..4],
+        finder.find_iter("aAaaa").collect::<Vec<_>>()
+    );
+    assert_eq!(Some(0), Finder::new("").find("abc"));
+    assert_eq!(0, Finder::new("").find_iter("abc").count());
+    assert_eq!(None, Finder::ascii_case_insensitive("abcd").find("abc"));
+
+    let matcher = Matcher::literal("TO", true);
+    assert_eq!(
+        vec![0..2, 10..12],
+        matcher.highlights("To be, or to not be")
+    );
+}
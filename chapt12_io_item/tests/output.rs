@@ -0,0 +1,111 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use chapt12_io_item::printer::highlight;
+use serde_json::Value;
+
+fn minigrep(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env_remove("IGNORE_CASE")
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn highlights_spans() {
+    assert_eq!(
+        "a \x1b[1;31mbc\x1b[0m d \x1b[1;31me\x1b[0m",
+        highlight("a bc d e", &[2..4, 7..8])
+    );
+    assert_eq!("plain", highlight("plain", &[]));
+}
+
+#[test]
+fn colors_output_when_asked() {
+    let output = minigrep(&["--color=always", "-n", "frog", "poem.txt"]);
+    assert_eq!(
+        "\x1b[32m7\x1b[0m\x1b[36m:\x1b[0mHow public, like a \x1b[1;31mfrog\x1b[0m\n",
+        stdout(&output)
+    );
+
+    // 输出到管道时 auto 不使用颜色
+    for args in [["--color", "frog"], ["--color=never", "frog"]] {
+        let output = minigrep(&[args[0], args[1], "poem.txt"]);
+        assert_eq!("How public, like a frog\n", stdout(&output));
+    }
+
+    let output = minigrep(&["--color=sometimes", "frog", "poem.txt"]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn prints_json_lines() {
+    let output = minigrep(&["--json", "--regex", "-C1", "bod(y)", "poem.txt"]);
+    assert!(output.status.success());
+    let lines: Vec<Value> = stdout(&output)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    // 上下文行不输出
+    assert_eq!(3, lines.len());
+    assert_eq!(
+        serde_json::json!({
+            "file": "poem.txt",
+            "line_number": 1,
+            "byte_offset": 0,
+            "text": "I'm nobody! Who are you?",
+            "submatches": [{ "start": 9, "end": 10, "text": "y" }],
+        }),
+        lines[0]
+    );
+    assert_eq!(2, lines[1]["line_number"]);
+    assert_eq!(25, lines[1]["byte_offset"]);
+    assert_eq!(6, lines[2]["line_number"]);
+
+    let output = minigrep(&["--json", "-c", "body", "poem.txt", "-"]);
+    assert_eq!(
+        "{\"file\":\"poem.txt\",\"count\":3}\n{\"file\":\"(standard input)\",\"count\":0}\n",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn json_offsets_use_raw_bytes() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(["--json", "lat", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"caf\xe9 \xff\xfe latte, lat\xc3\xa9\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let line: Value = serde_json::from_str(&stdout(&output)).unwrap();
+
+    // 每个无效的字节在 text 中变为 3 字节的 U+FFFD，偏移仍然按原始字节计算
+    assert_eq!(
+        "caf\u{fffd} \u{fffd}\u{fffd} latte, lat\u{e9}",
+        line["text"]
+    );
+    assert_eq!(
+        serde_json::json!([
+            { "start": 8, "end": 11, "text": "lat" },
+            { "start": 15, "end": 18, "text": "lat" },
+        ]),
+        line["submatches"]
+    );
+}
@@ -0,0 +1,133 @@
+use std::{
+    io,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+use chapt12_io_item::{parallel::ordered_map, search_files, Matcher, SearchOptions};
+
+mod common;
+
+use common::{temp_dir, write};
+
+#[test]
+fn emits_results_in_input_order() {
+    // 前面的任务更慢，完成顺序和输入顺序相反
+    let items: Vec<u64> = (0..8).collect();
+    let work = |item: &u64| {
+        thread::sleep(Duration::from_millis((8 - item) * 10));
+        item * 2
+    };
+    let mut seen = Vec::new();
+    ordered_map(&items, 4, work, |item, result| {
+        seen.push((*item, result.unwrap_or_else(|| work(item))));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!((0..8).map(|i| (i, i * 2)).collect::<Vec<_>>(), seen);
+}
+
+#[test]
+fn stops_when_emit_fails() {
+    let items: Vec<u32> = (0..100).collect();
+    let mut emitted = 0;
+    let result = ordered_map(
+        &items,
+        4,
+        |item| *item,
+        |item, _| {
+            emitted += 1;
+            if *item == 3 {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            Ok(())
+        },
+    );
+    assert_eq!(io::ErrorKind::BrokenPipe, result.unwrap_err().kind());
+    assert_eq!(4, emitted);
+}
+
+#[test]
+fn bounds_work_ahead_of_output() {
+    let items: Vec<usize> = (0..50).collect();
+    let started = AtomicUsize::new(0);
+    let mut direct = 0;
+    ordered_map(
+        &items,
+        2,
+        |item| started.fetch_max(*item, Ordering::SeqCst),
+        |item, result| {
+            // 2 个线程时最多提前 4 项
+            assert!(started.load(Ordering::SeqCst) < item + 4);
+            if result.is_none() {
+                direct += 1;
+            }
+            thread::sleep(Duration::from_millis(1));
+            Ok(())
+        },
+    )
+    .unwrap();
+    // 第一项总是在当前线程中直接处理
+    assert!(direct >= 1);
+}
+
+#[test]
+fn parallel_output_matches_sequential() {
+    let root = temp_dir("parallel");
+    for i in 0..40 {
+        let contents: String = (0..50)
+            .map(|line| {
+                format!(
+                    "file {} line {} {}\n",
+                    i,
+                    line,
+                    if line % 7 == 0 { "needle" } else { "hay" }
+                )
+            })
+            .collect();
+        write(&root, &format!("dir{}/file{:02}.txt", i % 4, i), contents);
+    }
+
+    let run = |threads: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+            .args(["-n", "-C1", "-j", threads, "needle", "."])
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let sequential = run("1");
+    assert_eq!(
+        40 * 8,
+        sequential.lines().filter(|l| l.contains("needle")).count()
+    );
+    assert_eq!(sequential, run("8"));
+
+    let paths: Vec<String> = (0..40)
+        .map(|i| {
+            root.join(format!("dir{}/file{:02}.txt", i % 4, i))
+                .display()
+                .to_string()
+        })
+        .collect();
+    let mut counts = Vec::new();
+    search_files(
+        &Matcher::literal("needle", false),
+        &paths,
+        &SearchOptions::default(),
+        4,
+        |path, matches| {
+            counts.push((path.to_string(), matches?.count));
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        paths,
+        counts.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>()
+    );
+    assert!(counts.iter().all(|(_, count)| *count == 8));
+}
@@ -0,0 +1,158 @@
+use std::{fs, path::Path, process::Command};
+
+use chapt12_io_item::{replace_file, Matcher};
+
+mod common;
+
+use common::{temp_dir, write};
+
+fn minigrep(dir: &Path, args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(args)
+        .current_dir(dir)
+        .env_remove("IGNORE_CASE")
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    )
+}
+
+#[test]
+fn replaces_matches() {
+    let matcher = Matcher::regex(r"(?P<key>\w+)=(\d+)", false).unwrap();
+    assert_eq!(
+        ("a: 1, bb: 22".to_string(), vec![0..4, 6..12]),
+        matcher.replace("a=1, bb=22", "$key: $2", true)
+    );
+    // 不展开时 $ 按原样插入
+    assert_eq!("$1, $1", matcher.replace("a=1, bb=22", "$1", false).0);
+
+    let matcher = Matcher::literal("STRASSE", true);
+    assert_eq!(
+        ("die Weg, der Weg".to_string(), vec![4..7, 13..16]),
+        matcher.replace("die Straße, der STRASSE", "Weg", false)
+    );
+    assert_eq!(
+        ("bc".to_string(), vec![]),
+        Matcher::literal("a", false).replace("abac", "", false)
+    );
+}
+
+#[test]
+fn prints_replaced_lines() {
+    let dir = temp_dir("replace_print");
+    write(&dir, "notes.txt", "name=alice\nage=30\nname=bob\n");
+    let (code, stdout) = minigrep(
+        &dir,
+        &[
+            "--regex",
+            "--replace",
+            "user ${1}",
+            "name=(\\w+)",
+            "notes.txt",
+        ],
+    );
+    assert_eq!(0, code);
+    assert_eq!("user alice\nuser bob\n", stdout);
+
+    let (_, stdout) = minigrep(
+        &dir,
+        &["-n", "-A1", "--replace", "NAME", "name", "notes.txt"],
+    );
+    assert_eq!("1:NAME=alice\n2-age=30\n3:NAME=bob\n", stdout);
+}
+
+#[test]
+fn rewrites_files_in_place() {
+    let dir = temp_dir("replace_in_place");
+    let contents = "fn old() {}\r\nlet x = old();\nkeep\n// old\n";
+    write(&dir, "src/a.rs", contents);
+    write(&dir, "src/b.rs", "nothing here\n");
+
+    // --dry-run 只输出差异，不修改文件
+    let (code, stdout) = minigrep(
+        &dir,
+        &["--replace", "new", "--in-place", "--dry-run", "old", "src"],
+    );
+    assert_eq!(0, code);
+    let path = Path::new("src").join("a.rs").display().to_string();
+    assert_eq!(
+        format!(
+            "--- {0}\n+++ {0}\n@@ -1,4 +1,4 @@\n\
+             -fn old() {{}}\r\n+fn new() {{}}\r\n\
+             -let x = old();\n+let x = new();\n \
+             keep\n\
+             -// old\n+// new\n",
+            path
+        ),
+        stdout
+    );
+    assert_eq!(contents, fs::read_to_string(dir.join("src/a.rs")).unwrap());
+
+    let (code, stdout) = minigrep(&dir, &["--replace", "new", "--in-place=.bak", "old", "src"]);
+    assert_eq!((0, String::new()), (code, stdout));
+    assert_eq!(
+        "fn new() {}\r\nlet x = new();\nkeep\n// new\n",
+        fs::read_to_string(dir.join("src/a.rs")).unwrap()
+    );
+    assert_eq!(
+        contents,
+        fs::read_to_string(dir.join("src/a.rs.bak")).unwrap()
+    );
+    // 没有匹配的文件不会被改写，也没有备份和临时文件留下
+    let mut files: Vec<_> = fs::read_dir(dir.join("src"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(vec!["a.rs", "a.rs.bak", "b.rs"], files);
+}
+
+#[test]
+fn diff_splits_distant_changes() {
+    let dir = temp_dir("replace_diff");
+    let lines: Vec<String> = (1..=20).map(|i| format!("line {}", i)).collect();
+    write(&dir, "f.txt", lines.join("\n"));
+
+    let matcher = Matcher::regex(r"^line (2|19)$", false).unwrap();
+    let path = dir.join("f.txt").display().to_string();
+    let edit = replace_file(&matcher, &path, "two\nlines", false)
+        .unwrap()
+        .unwrap();
+    let diff = edit.diff();
+    let hunks: Vec<&str> = diff.lines().filter(|line| line.starts_with("@@")).collect();
+    assert_eq!(vec!["@@ -1,5 +1,6 @@", "@@ -16,5 +17,6 @@"], hunks);
+    assert!(diff.ends_with("-line 19\n+two\n+lines\n line 20\n\\ No newline at end of file\n"));
+
+    assert!(
+        replace_file(&Matcher::literal("zzz", false), &path, "x", false)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn rewrites_symlink_targets() {
+    let dir = temp_dir("replace_symlink");
+    write(&dir, "target.txt", "old value\n");
+    std::os::unix::fs::symlink(dir.join("target.txt"), dir.join("link.txt")).unwrap();
+
+    let path = dir.join("link.txt").display().to_string();
+    let edit = replace_file(&Matcher::literal("old", false), &path, "new", false)
+        .unwrap()
+        .unwrap();
+    edit.write(None).unwrap();
+
+    // 链接仍然指向原来的文件，文件内容被修改
+    assert!(fs::symlink_metadata(dir.join("link.txt"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(
+        "new value\n",
+        fs::read_to_string(dir.join("target.txt")).unwrap()
+    );
+}
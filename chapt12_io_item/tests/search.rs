@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use chapt12_io_item::{search, search_case_insensitive};
+    use chapt12_io_item::{search, search_case_insensitive, search_reader, search_regex, Matcher};
+    use std::io::Cursor;
 
     #[test]
     fn case_sensitive() {
@@ -28,4 +29,120 @@ Trust me.";
             search_case_insensitive(query, contents)
         );
     }
+
+    #[test]
+    fn regex() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        assert_eq!(
+            vec!["Rust:", "Trust me."],
+            search_regex(r"[Rr]ust", contents).unwrap()
+        );
+        // 锚点按行匹配
+        assert_eq!(vec!["Rust:"], search_regex("^R", contents).unwrap());
+        assert_eq!(vec!["Rust:"], search_regex(":$", contents).unwrap());
+        assert_eq!(
+            vec!["Rust:", "Trust me."],
+            Matcher::regex("^(t|r)", true).unwrap().search(contents)
+        );
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let err = search_regex("(unclosed", "text").unwrap_err();
+        assert!(err.to_string().contains("invalid regex \"(unclosed\""));
+    }
+
+    #[test]
+    fn highlights() {
+        let matcher = Matcher::regex(r"\d+", false).unwrap();
+        assert_eq!(vec![4..6, 11..14], matcher.highlights("abc 12 def 345"));
+
+        // 有捕获组时只高亮捕获组
+        let matcher = Matcher::regex(r"(\w+)=(\d+)", false).unwrap();
+        assert_eq!(vec![0..1, 2..3, 4..5, 6..8], matcher.highlights("a=1 b=22"));
+
+        let matcher = Matcher::literal("ab", true);
+        assert_eq!(vec![0..2, 3..5], matcher.highlights("AB aB"));
+    }
+
+    #[test]
+    fn unicode_case_folding() {
+        let contents = "\
+Die Straße ist lang.
+DIE STRASSE IST LANG.
+ſtop the Σίσυφος
+İstanbul
+ıstanbul";
+
+        assert_eq!(
+            vec!["Die Straße ist lang.", "DIE STRASSE IST LANG."],
+            search_case_insensitive("strasse", contents)
+        );
+        assert_eq!(
+            vec!["Die Straße ist lang.", "DIE STRASSE IST LANG."],
+            search_case_insensitive("STRAẞE", contents)
+        );
+        assert_eq!(
+            vec!["ſtop the Σίσυφος"],
+            search_case_insensitive("STOP THE ΣΊΣΥΦΟΣ", contents)
+        );
+        // İ 折叠为 i 加上组合用的上点，无点的 ı 不折叠
+        assert_eq!(
+            vec!["İstanbul"],
+            search_case_insensitive("i̇STANBUL", contents)
+        );
+        assert_eq!(
+            vec!["ıstanbul"],
+            search_case_insensitive("ıSTANBUL", contents)
+        );
+        assert!(search_case_insensitive("ISTANBUL", contents).is_empty());
+        // 只匹配 ß 折叠结果的一部分不算匹配
+        assert!(search_case_insensitive("se", "Straße").is_empty());
+        assert!(search_case_insensitive("s ist", "Straße ist").is_empty());
+        assert_eq!(vec!["STRASSE"], search_case_insensitive("straß", "STRASSE"));
+    }
+
+    #[test]
+    fn folded_highlights_map_to_original_line() {
+        let matcher = Matcher::literal("STRASSE", true);
+        let line = "die Straße, die STRASSE";
+        let highlights = matcher.highlights(line);
+        assert_eq!(vec![4..11, 17..24], highlights);
+        assert_eq!("Straße", &line[highlights[0].clone()]);
+
+        // İ 是 2 字节，折叠后是 3 字节，后面的位置不能错开
+        let matcher = Matcher::literal("bul", true);
+        assert_eq!(vec![6..9], matcher.highlights("İstanBUL"));
+        assert!(matcher.is_match("İSTANBUL"));
+        assert_eq!(vec![0..2], Matcher::literal("ss", true).highlights("ß"));
+    }
+
+    #[test]
+    fn reader() {
+        let input = Cursor::new(b"Rust:\r\nsafe, \xff fast\nTrust me.".to_vec());
+        let mut found = Vec::new();
+        search_reader(
+            &Matcher::literal("st", false),
+            input,
+            false,
+            |number, line| {
+                found.push((number, line.to_string()));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                (1, "Rust:".to_string()),
+                (2, "safe, \u{fffd} fast".to_string()),
+                (3, "Trust me.".to_string())
+            ],
+            found
+        );
+    }
 }
@@ -0,0 +1,149 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use chapt12_io_item::{glob::Glob, Walker};
+
+mod common;
+
+use common::{temp_dir, write};
+
+fn glob(pattern: &str) -> Glob {
+    Glob::new(pattern).unwrap()
+}
+
+fn relative(root: &Path, files: Vec<std::io::Result<PathBuf>>) -> Vec<String> {
+    files
+        .into_iter()
+        .map(|file| {
+            let file = file.unwrap();
+            file.strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect()
+}
+
+// src/
+//   lib.rs, main.rs, notes.md, generated/out.rs, data.bin
+// target/debug/app.rs
+// logs/a.log, logs/keep.log
+// .gitignore
+fn source_tree(name: &str) -> PathBuf {
+    let root = temp_dir(name);
+    write(
+        &root,
+        ".gitignore",
+        "# build output\n/target/\n*.log\n!keep.log\n",
+    );
+    write(&root, "src/lib.rs", "pub fn hello() {}\n");
+    write(&root, "src/main.rs", "fn main() { hello() }\n");
+    write(&root, "src/notes.md", "hello notes\n");
+    write(&root, "src/generated/.gitignore", "out.rs\n");
+    write(&root, "src/generated/out.rs", "fn hello_generated() {}\n");
+    write(&root, "src/data.bin", b"hello\0\x01\x02");
+    write(&root, "target/debug/app.rs", "fn hello() {}\n");
+    write(&root, "logs/a.log", "hello log\n");
+    write(&root, "logs/keep.log", "hello kept log\n");
+    write(&root, ".git/config", "hello\n");
+    root
+}
+
+#[test]
+fn matches_globs() {
+    assert!(glob("*.rs").is_match("main.rs"));
+    assert!(!glob("*.rs").is_match("src/main.rs"));
+    assert!(glob("src/**/*.rs").is_match("src/main.rs"));
+    assert!(glob("src/**/*.rs").is_match("src/a/b/main.rs"));
+    assert!(glob("**/test_?.py").is_match("test_a.py"));
+    assert!(glob("file[0-9].[!c]*").is_match("file3.rs"));
+    assert!(!glob("file[0-9].[!c]*").is_match("file3.c"));
+    assert!(glob(r"\*.txt").is_match("*.txt"));
+    assert!(!glob(r"\*.txt").is_match("a.txt"));
+    assert!(Glob::new("[abc").is_err());
+}
+
+#[test]
+fn matches_many_stars_quickly() {
+    // 逐个回溯所有 * 的位置时需要指数级的时间
+    let path = "a".repeat(64);
+    assert!(!glob("*a*a*a*a*a*a*a*a*a*a*b").is_match(&path));
+    assert!(!glob("**a**a**a**a**a**a**a**b").is_match(&path));
+    assert!(glob("*a*a*a*a*a*a*a*a*a*a").is_match(&path));
+    assert!(!glob("**/a**/a**/a**/b").is_match(&"a/".repeat(32)));
+}
+
+#[test]
+fn respects_gitignore() {
+    let root = source_tree("walk_ignore");
+
+    let files = relative(&root, Walker::new().walk(&root));
+    assert_eq!(
+        vec![
+            ".gitignore",
+            "logs/keep.log",
+            "src/data.bin",
+            "src/generated/.gitignore",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/notes.md",
+        ],
+        files
+    );
+
+    let files = relative(&root, Walker::new().gitignore(false).walk(&root));
+    assert_eq!(
+        vec![
+            ".gitignore",
+            "logs/a.log",
+            "logs/keep.log",
+            "src/data.bin",
+            "src/generated/.gitignore",
+            "src/generated/out.rs",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/notes.md",
+            "target/debug/app.rs",
+        ],
+        files
+    );
+}
+
+#[test]
+fn filters_with_include_and_exclude() {
+    let root = source_tree("walk_filter");
+
+    let walker = Walker::new().include(glob("*.rs")).include(glob("*.md"));
+    assert_eq!(
+        vec!["src/lib.rs", "src/main.rs", "src/notes.md"],
+        relative(&root, walker.walk(&root))
+    );
+
+    let walker = Walker::new()
+        .gitignore(false)
+        .include(glob("**/*.rs"))
+        .exclude(glob("target"))
+        .exclude(glob("src/generated/**"));
+    assert_eq!(
+        vec!["src/lib.rs", "src/main.rs"],
+        relative(&root, walker.walk(&root))
+    );
+}
+
+#[test]
+fn prefixes_output_and_skips_binary_files() {
+    let root = source_tree("walk_cli");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chapt12_io_item"))
+        .args(["-n", "--exclude=*.md", "hello", "src", "logs/a.log"])
+        .current_dir(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        "src/lib.rs:1:pub fn hello() {}\nsrc/main.rs:1:fn main() { hello() }\nlogs/a.log:1:hello log\n",
+        String::from_utf8_lossy(&output.stdout).replace('\\', "/")
+    );
+}
@@ -0,0 +1,5 @@
+#!/bin/sh
+# CGI 示例：/cgi-bin/hello.sh?name=Ferris
+
+printf 'Content-Type: text/plain; charset=utf-8\r\n\r\n'
+echo "Hello from CGI! query: ${QUERY_STRING:-none}"
@@ -0,0 +1,341 @@
+use std::{
+    collections::BTreeMap,
+    env, fs, process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use chapt20_web_server::client::{parse_url, Client};
+
+// 压测工具：用 N 个并发连接请求目标地址，统计吞吐量和延迟
+// cargo run --release --bin bench -- -c 16 -d 10 -k http://127.0.0.1:7878/
+
+const USAGE: &str = "Usage: bench [OPTIONS] <URL>
+
+Options:
+  -c, --connections <N>  concurrent connections (default 8)
+  -d, --duration <SECS>  run for SECS seconds (default 10)
+  -n, --requests <N>     send N requests in total instead of running for a duration
+  -k, --keep-alive       reuse connections between requests
+  -t, --timeout <SECS>   per-request timeout (default 5)
+  -o, --output <FILE>    JSON summary path (default bench-summary.json)
+  -l, --label <TEXT>     label stored in the summary, e.g. a commit or pool size
+  -h, --help             print this help";
+
+struct Config {
+    url: String,
+    connections: usize,
+    duration: Duration,
+    requests: Option<usize>,
+    keep_alive: bool,
+    timeout: Duration,
+    output: String,
+    label: Option<String>,
+}
+
+impl Config {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Config, String> {
+        let mut config = Config {
+            url: String::new(),
+            connections: 8,
+            duration: Duration::from_secs(10),
+            requests: None,
+            keep_alive: false,
+            timeout: Duration::from_secs(5),
+            output: "bench-summary.json".to_string(),
+            label: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+            match arg.as_str() {
+                "-c" | "--connections" => config.connections = parse_number(&value(&arg)?)?,
+                "-d" | "--duration" => {
+                    config.duration = Duration::from_secs(parse_number(&value(&arg)?)? as u64)
+                }
+                "-n" | "--requests" => config.requests = Some(parse_number(&value(&arg)?)?),
+                "-k" | "--keep-alive" => config.keep_alive = true,
+                "-t" | "--timeout" => {
+                    config.timeout = Duration::from_secs(parse_number(&value(&arg)?)? as u64)
+                }
+                "-o" | "--output" => config.output = value(&arg)?,
+                "-l" | "--label" => config.label = Some(value(&arg)?),
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ if config.url.is_empty() => config.url = arg,
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        if config.url.is_empty() {
+            return Err("missing URL".to_string());
+        }
+        parse_url(&config.url).map_err(|e| e.to_string())?;
+        if config.connections == 0 {
+            return Err("--connections must be at least 1".to_string());
+        }
+        Ok(config)
+    }
+}
+
+fn parse_number(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| format!("invalid number: {}", s))
+}
+
+// 一个连接的统计结果
+#[derive(Default)]
+struct WorkerStats {
+    latencies: Vec<Duration>,
+    statuses: BTreeMap<u16, usize>,
+    errors: BTreeMap<String, usize>,
+}
+
+fn run_worker(config: &Config, remaining: &AtomicUsize, deadline: Instant) -> WorkerStats {
+    let client = Client::new()
+        .keep_alive(config.keep_alive)
+        .timeout(Some(config.timeout))
+        .max_redirects(0);
+    let mut stats = WorkerStats::default();
+
+    loop {
+        match config.requests {
+            // 请求数模式：从共享计数中领取一个请求
+            Some(_) => {
+                let taken = remaining
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+                if taken.is_err() {
+                    break;
+                }
+            }
+            None if Instant::now() >= deadline => break,
+            None => {}
+        }
+
+        let start = Instant::now();
+        match client.get(&config.url) {
+            Ok(response) => {
+                stats.latencies.push(start.elapsed());
+                *stats.statuses.entry(response.status).or_default() += 1;
+            }
+            Err(e) => {
+                *stats.errors.entry(format!("{:?}", e.kind())).or_default() += 1;
+            }
+        }
+    }
+
+    stats
+}
+
+#[derive(Serialize)]
+struct Latency {
+    min_ms: f64,
+    mean_ms: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    label: Option<String>,
+    url: String,
+    connections: usize,
+    keep_alive: bool,
+    elapsed_secs: f64,
+    requests: usize,
+    errors: usize,
+    requests_per_sec: f64,
+    latency: Option<Latency>,
+    statuses: BTreeMap<u16, usize>,
+    error_kinds: BTreeMap<String, usize>,
+}
+
+// 最近秩法求百分位，latencies 需要已排序
+fn percentile(latencies: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+fn summarize(config: &Config, elapsed: Duration, stats: Vec<WorkerStats>) -> Summary {
+    let mut latencies = Vec::new();
+    let mut statuses = BTreeMap::new();
+    let mut error_kinds = BTreeMap::new();
+    for worker in stats {
+        latencies.extend(worker.latencies);
+        for (status, count) in worker.statuses {
+            *statuses.entry(status).or_default() += count;
+        }
+        for (kind, count) in worker.errors {
+            *error_kinds.entry(kind).or_default() += count;
+        }
+    }
+    latencies.sort();
+
+    let latency = (!latencies.is_empty()).then(|| Latency {
+        min_ms: millis(latencies[0]),
+        mean_ms: millis(latencies.iter().sum::<Duration>() / latencies.len() as u32),
+        p50_ms: millis(percentile(&latencies, 50.0)),
+        p90_ms: millis(percentile(&latencies, 90.0)),
+        p99_ms: millis(percentile(&latencies, 99.0)),
+        max_ms: millis(latencies[latencies.len() - 1]),
+    });
+
+    Summary {
+        label: config.label.clone(),
+        url: config.url.clone(),
+        connections: config.connections,
+        keep_alive: config.keep_alive,
+        elapsed_secs: elapsed.as_secs_f64(),
+        requests: latencies.len(),
+        errors: error_kinds.values().sum(),
+        requests_per_sec: latencies.len() as f64 / elapsed.as_secs_f64(),
+        latency,
+        statuses,
+        error_kinds,
+    }
+}
+
+fn print_report(summary: &Summary) {
+    println!(
+        "{} connections, keep-alive {}, {:.2}s",
+        summary.connections,
+        if summary.keep_alive { "on" } else { "off" },
+        summary.elapsed_secs
+    );
+    println!(
+        "  requests: {}  errors: {}  throughput: {:.1} req/s",
+        summary.requests, summary.errors, summary.requests_per_sec
+    );
+    if let Some(latency) = &summary.latency {
+        println!(
+            "  latency: p50 {:.2}ms  p90 {:.2}ms  p99 {:.2}ms  max {:.2}ms",
+            latency.p50_ms, latency.p90_ms, latency.p99_ms, latency.max_ms
+        );
+    }
+    for (status, count) in &summary.statuses {
+        println!("  status {}: {}", status, count);
+    }
+    for (kind, count) in &summary.error_kinds {
+        println!("  error {}: {}", kind, count);
+    }
+}
+
+fn main() {
+    let config = Config::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("bench: {}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+    let config = Arc::new(config);
+
+    let remaining = Arc::new(AtomicUsize::new(config.requests.unwrap_or(0)));
+    let start = Instant::now();
+    let deadline = start + config.duration;
+
+    let handles: Vec<_> = (0..config.connections)
+        .map(|_| {
+            let config = Arc::clone(&config);
+            let remaining = Arc::clone(&remaining);
+            thread::spawn(move || run_worker(&config, &remaining, deadline))
+        })
+        .collect();
+    let stats = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+
+    let summary = summarize(&config, start.elapsed(), stats);
+    print_report(&summary);
+
+    let json = serde_json::to_string_pretty(&summary).unwrap();
+    if let Err(e) = fs::write(&config.output, json + "\n") {
+        eprintln!("bench: failed to write {}: {}", config.output, e);
+        process::exit(1);
+    }
+    println!("Summary written to {}", config.output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(list: &[u64]) -> Vec<Duration> {
+        list.iter().map(|&n| Duration::from_millis(n)).collect()
+    }
+
+    fn config() -> Config {
+        Config::parse(["http://127.0.0.1:1/".to_string()].into_iter()).unwrap()
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let latencies = ms(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(Duration::from_millis(5), percentile(&latencies, 50.0));
+        assert_eq!(Duration::from_millis(9), percentile(&latencies, 90.0));
+        assert_eq!(Duration::from_millis(10), percentile(&latencies, 99.0));
+        assert_eq!(Duration::from_millis(1), percentile(&latencies, 0.0));
+        assert_eq!(Duration::from_millis(10), percentile(&latencies, 100.0));
+
+        let single = ms(&[7]);
+        assert_eq!(Duration::from_millis(7), percentile(&single, 50.0));
+        assert_eq!(Duration::from_millis(7), percentile(&single, 99.0));
+    }
+
+    #[test]
+    fn summarize_merges_workers() {
+        let first = WorkerStats {
+            latencies: ms(&[30, 10]),
+            statuses: BTreeMap::from([(200, 2)]),
+            errors: BTreeMap::from([("TimedOut".to_string(), 1)]),
+        };
+        let second = WorkerStats {
+            latencies: ms(&[20, 40]),
+            statuses: BTreeMap::from([(200, 1), (404, 1)]),
+            errors: BTreeMap::from([("TimedOut".to_string(), 2)]),
+        };
+
+        let summary = summarize(&config(), Duration::from_secs(2), vec![first, second]);
+        assert_eq!(4, summary.requests);
+        assert_eq!(3, summary.errors);
+        assert_eq!(2.0, summary.requests_per_sec);
+        assert_eq!(BTreeMap::from([(200, 3), (404, 1)]), summary.statuses);
+        assert_eq!(Some(&3), summary.error_kinds.get("TimedOut"));
+
+        let latency = summary.latency.unwrap();
+        assert_eq!(10.0, latency.min_ms);
+        assert_eq!(25.0, latency.mean_ms);
+        assert_eq!(20.0, latency.p50_ms);
+        assert_eq!(40.0, latency.p90_ms);
+        assert_eq!(40.0, latency.max_ms);
+    }
+
+    #[test]
+    fn summarize_without_responses() {
+        let stats = WorkerStats {
+            errors: BTreeMap::from([("ConnectionRefused".to_string(), 5)]),
+            ..WorkerStats::default()
+        };
+
+        let summary = summarize(&config(), Duration::from_secs(1), vec![stats]);
+        assert_eq!(0, summary.requests);
+        assert_eq!(5, summary.errors);
+        assert_eq!(0.0, summary.requests_per_sec);
+        assert!(summary.latency.is_none());
+    }
+}
@@ -1,90 +1,176 @@
 use std::{
-    fs,
-    io::{BufRead, BufReader, Write},
-    net::{TcpListener, TcpStream},
+    sync::Arc,
     thread,
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use chapt20_web_server::ThreadPool;
+use serde::{Deserialize, Serialize};
+
+use chapt20_web_server::{
+    cgi::Cgi,
+    cors::Cors,
+    error::{ErrorPages, HttpError},
+    extract::{FromRequest, Json, Query},
+    limit::Limiter,
+    request::Request,
+    response::Response,
+    security::SecurityHeaders,
+    server::{Handler, Server},
+    session::{MemoryStore, Sessions},
+    sse::{self, Event},
+    static_files::StaticFiles,
+    template::{Context, Templates},
+    websocket,
+};
 
 //将单线程 server 变为多线程 server
 // 使用线程池改善吞吐量
 
 const HOST: &str = "127.0.0.1:7878";
 const THREAD_SIZE: usize = 4;
+const ROOT: &str = "./chapt20_web_server/public";
+const TEMPLATES: &str = "./chapt20_web_server/templates";
+const CGI_BIN: &str = "./chapt20_web_server/cgi-bin";
+// 静态文件缓存的大小
+const CACHE_SIZE: usize = 16 * 1024 * 1024;
+// 每个 IP 的连接数和请求速率限制，用 bench 压测时注意这些限制也会生效
+const MAX_CONNECTIONS: usize = 256;
+// 小于线程数，一个 IP 的连接不能占满线程池
+const MAX_CONNECTIONS_PER_IP: usize = THREAD_SIZE / 2;
+const REQUESTS_PER_SECOND: f64 = 1000.0;
+const BURST: u32 = 2000;
+// 允许跨域调用 API 的前端地址
+const CORS_ORIGIN: &str = "http://localhost:3000";
+// 会话 cookie 的签名密钥，仅用于本地演示
+const SESSION_SECRET: &[u8] = b"chapt20-web-server-demo-secret-key";
+
+#[derive(Deserialize)]
+struct Greet {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Greeting {
+    message: String,
+}
 
 fn main() {
     // 流（stream）代表一个客户端和服务端之间打开的连接
     // 连接（connection）代表客户端连接服务端、服务端生成响应以及服务端关闭连接的全部请求 / 响应过程
 
-    // 监听 TCP 连接
-    let listener = TcpListener::bind(HOST).unwrap();
-
-    // 创建容量为4的线程池
-    let pool = ThreadPool::new(THREAD_SIZE);
-
-    for stream in listener.incoming()/*.take(2)*/ {
-        let stream = stream.unwrap();
-        println!("Connection established!");
-        // println!("{:?}", stream);
-
-        // 为每一个请求分配线程 这种方式可以 但是不合适
-        // thread::spawn(|| {
-        // 读取请求
-        // handle_connection(stream);
-        // });
-
-        // 创建有限数量的线程
-        pool.execute(|| {
-            handle_connection(stream);
-        });
-    }
-}
-
-// 处理请求方法
-fn handle_connection(mut stream: TcpStream) {
-    let buf_reader = BufReader::new(&mut stream);
-    // let http_request = buf_reader
-    //     .lines()
-    //     .map(|result| result.unwrap())
-    //     .take_while(|line| !line.is_empty())
-    //     .collect::<Vec<_>>();
+    let static_files = StaticFiles::new(ROOT)
+        .cache_control("public, max-age=60")
+        .with_cache(CACHE_SIZE);
+    // debug 构建时修改模板不需要重启
+    let templates = Templates::new(TEMPLATES).hot_reload(cfg!(debug_assertions));
+
+    // 用会话记录访问次数
+    let visits = Sessions::new(MemoryStore::new(), SESSION_SECRET).wrap(|_, session| {
+        let count: u32 = session
+            .get("visits")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        session.insert("visits", (count + 1).to_string());
+        Response::text(
+            200,
+            format!("You have visited this page {} times.\n", count + 1),
+        )
+    });
+
+    let limiter = Arc::new(
+        Limiter::new()
+            .max_connections(MAX_CONNECTIONS)
+            .max_connections_per_ip(MAX_CONNECTIONS_PER_IP)
+            .rate_limit(REQUESTS_PER_SECOND, BURST),
+    );
+    let stats = Arc::clone(&limiter);
 
-    // println!("Request: {:#?}", http_request);
+    // /cgi-bin/ 下的请求交给 CGI_BIN 目录中的脚本
+    let cgi = Cgi::new("/cgi-bin", CGI_BIN).timeout(Duration::from_secs(10));
 
-    let request_line = buf_reader.lines().next().unwrap().unwrap();
+    // 处理函数出错时返回错误页面，不会让工作线程 panic
+    let error_pages = ErrorPages::new().page(500, format!("{}/500.html", ROOT));
 
     // 验证请求并有选择的进行响应
-    // 编写响应
-    // let (status_line, filename) = if buf_reader.lines().next().unwrap().unwrap() == "GET / HTTP/1.1"
-    let (status_line, filename) = match &request_line[..] {
-        "GET / HTTP/1.1" => ("HTTP/1.1 200 OK", ".\\chapt20_web_server\\hello.html"),
-        "GET /sleep HTTP/1.1" => {
+    let handler = error_pages.wrap(move |req: &Request| match req.path.as_str() {
+        "/" => {
+            let query = Query::from_request(req)?;
+            let context = Context::new().with("name", query.get("name"));
+            Ok(Response::html(
+                200,
+                templates.render("hello.html", &context)?,
+            ))
+        }
+        "/api/greet" => greet(req),
+        "/visits" => Ok(visits.handle(req)),
+        "/stats" => Ok(Response::json(200, &stats.stats())),
+        // WebSocket 回显，连接会一直占用一个工作线程
+        "/ws/echo" => Ok(websocket::upgrade(req, websocket::echo)),
+        // 每秒推送一次服务器时间，服务器关闭时连接随线程池结束
+        "/events" => Ok(clock(req)),
+        "/sleep" => {
             thread::sleep(Duration::from_secs(5));
-            ("HTTP/1.1 200 OK", ".\\chapt20_web_server\\hello.html")
+            Ok(Response::html(
+                200,
+                templates.render("hello.html", &Context::new())?,
+            ))
         }
+        _ if cgi.matches(req) => Ok(cgi.handle(req)),
+        _ => {
+            let response = static_files.serve(req);
+            if response.status == 404 {
+                let context = Context::new().with("path", &req.path);
+                Ok(Response::html(404, templates.render("404.html", &context)?))
+            } else {
+                Ok(response)
+            }
+        }
+    });
+
+    let handler = Cors::new()
+        .allow_origin(CORS_ORIGIN)
+        .allow_headers(&["Content-Type"])
+        .wrap(handler);
+    let handler = SecurityHeaders::new().wrap(handler);
+
+    // 监听 TCP 连接，创建容量为4的线程池
+    let server = Server::bind(HOST, THREAD_SIZE, handler)
+        .unwrap()
+        .limiter(limiter);
+    server.run();
+}
 
-        _ => ("HTTP/1.1 404 NOT FOUND", ".\\chapt20_web_server\\404.html"),
-    };
-
-    let contents = fs::read_to_string(filename).unwrap();
-    let length = contents.len();
-
-    let response = format!(
-        "{}\r\nContent-Length: {}\r\n\r\n{}",
-        status_line, length, contents
-    );
+// POST /api/greet {"name": "Ferris"}
+fn greet(req: &Request) -> Result<Response, HttpError> {
+    if req.method != "POST" {
+        return Ok(Response::new(405).with_header("Allow", "POST"));
+    }
 
-    stream.write_all(response.as_bytes()).unwrap();
+    let Json(greet) = Json::<Greet>::from_request(req)?;
+    let greeting = Greeting {
+        message: format!("Hello, {}!", greet.name),
+    };
+    Ok(Response::json(200, &greeting))
 }
 
-// request
-// Method Request-URI HTTP-Version CRLF
-// headers CRLF
-// message-body
-
-// response
-// HTTP-Version Status-Code Reason-Phrase CRLF
-// headers CRLF
-// message-body
+// SSE 演示：客户端断开后 send 失败，推送线程随之结束
+fn clock(req: &Request) -> Response {
+    let (sender, response) = sse::channel();
+    let mut id = sse::last_event_id(req)
+        .and_then(|id| id.parse::<u64>().ok())
+        .unwrap_or(0);
+    thread::spawn(move || loop {
+        id += 1;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let event = Event::new(now.as_secs().to_string())
+            .id(id.to_string())
+            .event("tick");
+        if sender.send(&event).is_err() {
+            break;
+        }
+        thread::sleep(Duration::from_secs(1));
+    });
+    response
+}
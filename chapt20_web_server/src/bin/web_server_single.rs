@@ -38,14 +38,31 @@ fn handle_connection(mut stream: TcpStream) {
 
     // 验证请求并有选择的进行响应
     // 编写响应
-    let (status_line, filename) = if buf_reader.lines().next().unwrap().unwrap() == "GET / HTTP/1.1"
-    {
-        ("HTTP/1.1 200 OK", ".\\chapt20_web_server\\hello.html")
+    // 连接在发送请求行之前就断开时直接返回
+    let request_line = match buf_reader.lines().next() {
+        Some(Ok(line)) => line,
+        _ => return,
+    };
+    let (status_line, filename) = if request_line == "GET / HTTP/1.1" {
+        ("HTTP/1.1 200 OK", "./chapt20_web_server/public/hello.html")
     } else {
-        ("HTTP/1.1 404 NOT FOUND", ".\\chapt20_web_server\\404.html")
+        (
+            "HTTP/1.1 404 NOT FOUND",
+            "./chapt20_web_server/public/404.html",
+        )
     };
 
-    let contents = fs::read_to_string(filename).unwrap();
+    // 文件读取失败时返回 500，而不是让整个 server panic
+    let (status_line, contents) = match fs::read_to_string(filename) {
+        Ok(contents) => (status_line, contents),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", filename, e);
+            (
+                "HTTP/1.1 500 INTERNAL SERVER ERROR",
+                String::from("Internal Server Error\n"),
+            )
+        }
+    };
     let length = contents.len();
 
     let response = format!(
@@ -53,7 +70,9 @@ fn handle_connection(mut stream: TcpStream) {
         status_line, length, contents
     );
 
-    stream.write_all(response.as_bytes()).unwrap();
+    if let Err(e) = stream.write_all(response.as_bytes()) {
+        eprintln!("Failed to write response: {}", e);
+    }
 }
 
 // request
@@ -0,0 +1,315 @@
+use std::{
+    io::{self, Read, Write},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{http::Headers, request::Request, response::Response, server::Handler};
+
+// 脚本输出的大小上限，超过时返回 502
+const MAX_OUTPUT_SIZE: u64 = 10 * 1024 * 1024;
+
+// 等待脚本时检查超时的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// CGI/1.1：把 `prefix/脚本名/附加路径` 的请求交给 dir 下的可执行文件处理
+pub struct Cgi {
+    prefix: String,
+    dir: PathBuf,
+    timeout: Duration,
+    env: Vec<(String, String)>,
+}
+
+impl Cgi {
+    /// `/cgi-bin/hello.sh/a?x=1` 会运行 `dir/hello.sh`，`PATH_INFO` 为 `/a`。
+    pub fn new(prefix: &str, dir: impl Into<PathBuf>) -> Cgi {
+        Cgi {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            dir: dir.into(),
+            timeout: Duration::from_secs(30),
+            env: Vec::new(),
+        }
+    }
+
+    /// 脚本运行超过 `timeout` 时结束进程并返回 504。
+    pub fn timeout(mut self, timeout: Duration) -> Cgi {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 额外传给脚本的环境变量。
+    pub fn env(mut self, name: &str, value: &str) -> Cgi {
+        self.env.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn matches(&self, req: &Request) -> bool {
+        req.path
+            .strip_prefix(&self.prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    pub fn run(&self, req: &Request) -> Response {
+        // 拆出脚本名和 PATH_INFO
+        let rest = match req.path.strip_prefix(&self.prefix) {
+            Some(rest) if rest.starts_with('/') => &rest[1..],
+            _ => return Response::text(404, "Not Found\n"),
+        };
+        let (script, path_info) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+        if script.is_empty() || script.starts_with('.') || script.contains('\\') {
+            return Response::text(404, "Not Found\n");
+        }
+        // 子进程的工作目录是 dir，脚本路径需要是绝对路径
+        let path = match self.dir.join(script).canonicalize() {
+            Ok(path) if path.is_file() => path,
+            _ => return Response::text(404, "Not Found\n"),
+        };
+
+        let mut command = Command::new(&path);
+        command
+            .current_dir(&self.dir)
+            .env_clear()
+            .envs(self.environment(req, script, path_info))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit());
+
+        let output = match command.spawn() {
+            Ok(child) => self.communicate(child, &req.body),
+            Err(e) => Err(e),
+        };
+        match output {
+            Ok(output) => parse_output(&output).unwrap_or_else(|message| {
+                eprintln!("CGI script {} returned {}", path.display(), message);
+                Response::text(502, "Bad Gateway\n")
+            }),
+            Err(e) if e.kind() == io::ErrorKind::FileTooLarge => {
+                eprintln!(
+                    "CGI script {} output exceeds {} bytes",
+                    path.display(),
+                    MAX_OUTPUT_SIZE
+                );
+                Response::text(502, "Bad Gateway\n")
+            }
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                eprintln!("CGI script {} timed out", path.display());
+                Response::text(504, "Gateway Timeout\n")
+            }
+            Err(e) => {
+                eprintln!("CGI script {} failed: {}", path.display(), e);
+                Response::text(500, "Internal Server Error\n")
+            }
+        }
+    }
+
+    // 标准的 CGI 环境变量，请求 header 转为 HTTP_*
+    fn environment(&self, req: &Request, script: &str, path_info: &str) -> Vec<(String, String)> {
+        let host = req.header("Host").unwrap_or("");
+        let (server_name, server_port) = match host.rsplit_once(':') {
+            Some((name, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => {
+                (name, port)
+            }
+            _ => (host, "80"),
+        };
+
+        let mut env = vec![
+            ("GATEWAY_INTERFACE", "CGI/1.1".to_string()),
+            ("SERVER_PROTOCOL", req.version.clone()),
+            ("SERVER_SOFTWARE", "chapt20_web_server".to_string()),
+            ("SERVER_NAME", server_name.to_string()),
+            ("SERVER_PORT", server_port.to_string()),
+            ("REQUEST_METHOD", req.method.clone()),
+            ("SCRIPT_NAME", format!("{}/{}", self.prefix, script)),
+            ("PATH_INFO", path_info.to_string()),
+            ("QUERY_STRING", req.query.clone().unwrap_or_default()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect::<Vec<_>>();
+
+        if let Some(peer) = req.peer_addr {
+            env.push(("REMOTE_ADDR".to_string(), peer.ip().to_string()));
+            env.push(("REMOTE_PORT".to_string(), peer.port().to_string()));
+        }
+        if !req.body.is_empty() {
+            env.push(("CONTENT_LENGTH".to_string(), req.body.len().to_string()));
+        }
+        if let Some(content_type) = req.header("Content-Type") {
+            env.push(("CONTENT_TYPE".to_string(), content_type.to_string()));
+        }
+        if let Ok(path) = std::env::var("PATH") {
+            env.push(("PATH".to_string(), path));
+        }
+
+        for (name, value) in req.headers.iter() {
+            // Content-* 已经有专门的变量；Proxy 会被当作 HTTP_PROXY（httpoxy）
+            if name.eq_ignore_ascii_case("Content-Type")
+                || name.eq_ignore_ascii_case("Content-Length")
+                || name.eq_ignore_ascii_case("Proxy")
+                || name.eq_ignore_ascii_case("Authorization")
+            {
+                continue;
+            }
+            let key = format!("HTTP_{}", name.to_ascii_uppercase().replace('-', "_"));
+            match env.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, existing)) => {
+                    existing.push_str(", ");
+                    existing.push_str(value);
+                }
+                None => env.push((key, value.to_string())),
+            }
+        }
+
+        env.extend(self.env.iter().cloned());
+        env
+    }
+
+    // 写入 stdin、读取 stdout 分别在单独的线程中进行，避免管道写满时互相等待
+    //
+    // 脚本在后台启动的进程会继承 stdout，脚本退出后读取仍可能一直等待，
+    // 所以读取也受超时限制。超时后读取线程留在后台，直到这些进程关闭 stdout。
+    fn communicate(&self, mut child: Child, body: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stdin = child.stdin.take().unwrap();
+        let body = body.to_vec();
+        thread::spawn(move || {
+            // 脚本可能不读 stdin 就退出，这时写入失败是正常的
+            let _ = stdin.write_all(&body);
+        });
+
+        let stdout = child.stdout.take().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            let result = stdout
+                .take(MAX_OUTPUT_SIZE + 1)
+                .read_to_end(&mut output)
+                .and_then(|_| {
+                    if output.len() as u64 > MAX_OUTPUT_SIZE {
+                        return Err(io::Error::new(
+                            io::ErrorKind::FileTooLarge,
+                            "CGI output too large",
+                        ));
+                    }
+                    Ok(output)
+                });
+            let _ = sender.send(result);
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let output = loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(output) => break output,
+                Err(RecvTimeoutError::Disconnected) => {
+                    break Err(io::Error::other("CGI output reader panicked"))
+                }
+                Err(RecvTimeoutError::Timeout) if Instant::now() >= deadline => {
+                    break Err(io::Error::from(io::ErrorKind::TimedOut))
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        };
+        if output.is_err() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return output;
+        }
+
+        // 关闭 stdout 之后脚本仍然可能在运行
+        loop {
+            if child.try_wait()?.is_some() {
+                return output;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::from(io::ErrorKind::TimedOut));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Handler for Cgi {
+    fn handle(&self, req: &Request) -> Response {
+        self.run(req)
+    }
+}
+
+// 脚本输出：header、空行、body，header 行可以用 \n 或 \r\n 结尾
+fn parse_output(output: &[u8]) -> Result<Response, String> {
+    let (head, body) = match find_blank_line(output) {
+        Some((end, body_start)) => (&output[..end], &output[body_start..]),
+        None => return Err("no header section".to_string()),
+    };
+    let head = std::str::from_utf8(head).map_err(|_| "non UTF-8 headers".to_string())?;
+
+    let mut headers = Headers::new();
+    let mut status = None;
+    for line in head.lines() {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("malformed header line {:?}", line))?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("Status") {
+            // Status: 404 Not Found
+            let code = value.split(' ').next().unwrap_or("");
+            status = Some(
+                code.parse::<u16>()
+                    .map_err(|_| format!("bad status {:?}", value))?,
+            );
+        } else if name.eq_ignore_ascii_case("Content-Length")
+            || name.eq_ignore_ascii_case("Transfer-Encoding")
+        {
+            // 长度以实际读到的输出为准，写响应时重新计算
+            continue;
+        } else {
+            headers.append(name, value);
+        }
+    }
+
+    let status = match status {
+        Some(status) => status,
+        // 只有 Location 的是本地或客户端重定向
+        None if headers.contains("Location") => 302,
+        None if headers.contains("Content-Type") => 200,
+        None => return Err("neither Content-Type nor Location".to_string()),
+    };
+    // 1xx 是中间响应，脚本不能用它结束请求
+    if !(200..600).contains(&status) {
+        return Err(format!("bad status {}", status));
+    }
+
+    // 204 和 304 不能带 body
+    let body = if matches!(status, 204 | 304) {
+        &[][..]
+    } else {
+        body
+    };
+    let mut response = Response::new(status).with_body(body);
+    response.headers = headers;
+    Ok(response)
+}
+
+// 返回 header 结束的位置和 body 开始的位置
+fn find_blank_line(output: &[u8]) -> Option<(usize, usize)> {
+    let mut i = 0;
+    while i < output.len() {
+        if output[i..].starts_with(b"\r\n\r\n") {
+            return Some((i, i + 4));
+        }
+        if output[i..].starts_with(b"\n\n") {
+            return Some((i, i + 2));
+        }
+        if output[i..].starts_with(b"\n\r\n") {
+            return Some((i, i + 3));
+        }
+        i += 1;
+    }
+    None
+}
@@ -0,0 +1,400 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::{
+    http::{invalid_data, read_chunked},
+    request::Request,
+    response::{Body, Response},
+};
+
+// 响应 body 的默认大小上限
+const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+// 每个地址最多保留的空闲连接数
+const MAX_IDLE_PER_HOST: usize = 8;
+
+// 阻塞的 HTTP/1.1 客户端，只支持 http://
+// 空闲连接按 host:port 保存，后续请求会复用
+pub struct Client {
+    idle: Mutex<HashMap<String, Vec<BufReader<TcpStream>>>>,
+    connect_timeout: Duration,
+    timeout: Option<Duration>,
+    max_redirects: usize,
+    max_body_size: usize,
+    keep_alive: bool,
+}
+
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client {
+            idle: Mutex::new(HashMap::new()),
+            connect_timeout: Duration::from_secs(10),
+            timeout: Some(Duration::from_secs(30)),
+            max_redirects: 5,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            keep_alive: true,
+        }
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Client {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// 读写的超时时间，`None` 表示一直等待。
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Client {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 最多跟随的重定向次数，0 表示不跟随。
+    pub fn max_redirects(mut self, max: usize) -> Client {
+        self.max_redirects = max;
+        self
+    }
+
+    /// 设置响应 body 的大小上限，超过时返回 `FileTooLarge`。
+    pub fn max_body_size(mut self, bytes: usize) -> Client {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// 关闭后每个请求都使用新连接，并发送 `Connection: close`。
+    pub fn keep_alive(mut self, keep_alive: bool) -> Client {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn get(&self, url: &str) -> io::Result<Response> {
+        let (addr, target) = parse_url(url)?;
+        self.send(&addr, Request::new("GET", &target))
+    }
+
+    pub fn post(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: impl Into<Vec<u8>>,
+    ) -> io::Result<Response> {
+        let (addr, target) = parse_url(url)?;
+        let mut req = Request::new("POST", &target);
+        req.headers.insert("Content-Type", content_type);
+        req.body = body.into();
+        self.send(&addr, req)
+    }
+
+    /// 把 `req` 发送到 `addr`（`host:port`），返回 body 已经读完的响应。
+    ///
+    /// 301、302、303 重定向改用 GET 并丢弃 body，307、308 原样重发。
+    ///
+    /// # Errors
+    ///
+    /// 连接失败、超时或响应格式错误时返回错误，重定向次数过多时返回 `InvalidData`。
+    pub fn send(&self, addr: &str, mut req: Request) -> io::Result<Response> {
+        let mut addr = addr.to_string();
+
+        for _ in 0..=self.max_redirects {
+            let response = self.send_once(&addr, &req).map_err(|e| {
+                // 读超时在部分平台上是 WouldBlock
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    io::Error::new(io::ErrorKind::TimedOut, e)
+                } else {
+                    e
+                }
+            })?;
+
+            let location = match response.headers.get("Location") {
+                Some(location) if matches!(response.status, 301 | 302 | 303 | 307 | 308) => {
+                    location.to_string()
+                }
+                _ => return Ok(response),
+            };
+            if self.max_redirects == 0 {
+                return Ok(response);
+            }
+
+            let target = if has_scheme(&location) {
+                let (new_addr, target) = parse_url(&location)?;
+                if new_addr != addr {
+                    // 换了主机，原来的 Host 和凭据都不能带过去
+                    for name in ["Host", "Authorization", "Cookie", "Proxy-Authorization"] {
+                        req.headers.remove(name);
+                    }
+                    addr = new_addr;
+                }
+                target
+            } else {
+                resolve_location(&req.path, &location)
+            };
+
+            let mut next = Request::new(&req.method, &target);
+            next.headers = req.headers.clone();
+            if matches!(response.status, 307 | 308) {
+                next.body = std::mem::take(&mut req.body);
+            } else {
+                if !req.is_head() {
+                    next.method = "GET".to_string();
+                }
+                next.headers.remove("Content-Type");
+                next.headers.remove("Content-Length");
+            }
+            req = next;
+        }
+
+        Err(invalid_data("too many redirects"))
+    }
+
+    fn send_once(&self, addr: &str, req: &Request) -> io::Result<Response> {
+        if let Some(conn) = self.take_idle(addr) {
+            match self.exchange(conn, addr, req) {
+                Ok(response) => return Ok(response),
+                // 复用的连接可能已被服务器关闭，换新连接重试一次
+                Err(e) if is_stale(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let conn = self.connect(addr)?;
+        self.exchange(conn, addr, req)
+    }
+
+    fn connect(&self, addr: &str) -> io::Result<BufReader<TcpStream>> {
+        let mut last_error = io::Error::from(io::ErrorKind::AddrNotAvailable);
+        for socket_addr in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&socket_addr, self.connect_timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(self.timeout)?;
+                    stream.set_write_timeout(self.timeout)?;
+                    stream.set_nodelay(true)?;
+                    return Ok(BufReader::new(stream));
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn take_idle(&self, addr: &str) -> Option<BufReader<TcpStream>> {
+        self.idle.lock().unwrap().get_mut(addr)?.pop()
+    }
+
+    fn put_idle(&self, addr: &str, conn: BufReader<TcpStream>) {
+        let mut idle = self.idle.lock().unwrap();
+        let conns = idle.entry(addr.to_string()).or_default();
+        if conns.len() < MAX_IDLE_PER_HOST {
+            conns.push(conn);
+        }
+    }
+
+    fn exchange(
+        &self,
+        mut conn: BufReader<TcpStream>,
+        addr: &str,
+        req: &Request,
+    ) -> io::Result<Response> {
+        conn.get_mut().write_all(&self.request_head(addr, req))?;
+        conn.get_mut().write_all(&req.body)?;
+        conn.get_mut().flush()?;
+
+        // 跳过 100 Continue 之类的中间响应
+        let mut response = loop {
+            let response = Response::read_head(&mut conn)?;
+            if !matches!(response.status, 100..=199) || response.status == 101 {
+                break response;
+            }
+        };
+
+        let close = response
+            .headers
+            .get("Connection")
+            .is_some_and(|c| c.eq_ignore_ascii_case("close"));
+        let chunked = response
+            .headers
+            .get("Transfer-Encoding")
+            .is_some_and(|te| te.to_ascii_lowercase().contains("chunked"));
+        let len = match response.headers.get("Content-Length") {
+            Some(len) => Some(
+                len.trim()
+                    .parse::<usize>()
+                    .map_err(|_| invalid_data("bad Content-Length"))?,
+            ),
+            None => None,
+        };
+
+        let bodiless = req.is_head() || matches!(response.status, 100..=199 | 204 | 304);
+        let (body, reusable) = if bodiless {
+            (Vec::new(), true)
+        } else if chunked {
+            // body 已经解码，去掉 Transfer-Encoding 以免误导调用者
+            response.headers.remove("Transfer-Encoding");
+            (read_chunked(&mut conn, self.max_body_size)?, true)
+        } else if let Some(len) = len {
+            if len > self.max_body_size {
+                return Err(io::Error::from(io::ErrorKind::FileTooLarge));
+            }
+            let mut body = vec![0; len];
+            conn.read_exact(&mut body)?;
+            (body, true)
+        } else {
+            // 没有长度时 body 一直到服务器关闭连接
+            let mut body = Vec::new();
+            conn.by_ref()
+                .take(self.max_body_size as u64 + 1)
+                .read_to_end(&mut body)?;
+            if body.len() > self.max_body_size {
+                return Err(io::Error::from(io::ErrorKind::FileTooLarge));
+            }
+            (body, false)
+        };
+
+        if self.keep_alive
+            && reusable
+            && !close
+            && response.status != 101
+            && conn.buffer().is_empty()
+        {
+            self.put_idle(addr, conn);
+        }
+
+        response.body = Body::Bytes(body);
+        Ok(response)
+    }
+
+    fn request_head(&self, addr: &str, req: &Request) -> Vec<u8> {
+        let mut head = format!("{} {}", req.method, req.path);
+        if let Some(query) = &req.query {
+            head.push('?');
+            head.push_str(query);
+        }
+        head.push_str(" HTTP/1.1\r\n");
+
+        let mut headers = req.headers.clone();
+        if !headers.contains("Host") {
+            headers.insert("Host", addr);
+        }
+        if !self.keep_alive {
+            headers.insert("Connection", "close");
+        }
+        headers.remove("Transfer-Encoding");
+        if !req.body.is_empty() || matches!(req.method.as_str(), "POST" | "PUT" | "PATCH") {
+            headers.insert("Content-Length", req.body.len().to_string());
+        }
+
+        for (name, value) in headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+}
+
+// 服务器关闭空闲连接后，写入或读取响应头会出现这些错误
+fn is_stale(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+/// 把 `http://host:port/path?query` 拆成 `host:port` 和 `/path?query`。
+///
+/// # Errors
+///
+/// 不是 `http://` URL 时返回 `InvalidInput`。
+// `http:` 之类的 scheme 开头的是绝对 URL
+fn has_scheme(location: &str) -> bool {
+    location.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+// 把相对的 Location 解析成相对当前路径的请求目标
+fn resolve_location(path: &str, location: &str) -> String {
+    let location = location.split('#').next().unwrap_or("");
+    if location.starts_with('/') {
+        return location.to_string();
+    }
+    if location.starts_with('?') {
+        return format!("{}{}", path, location);
+    }
+    // 替换当前路径的最后一段，`/a/b` + `c` 得到 `/a/c`
+    let dir = match path.rfind('/') {
+        Some(i) => &path[..=i],
+        None => "/",
+    };
+    format!("{}{}", dir, location)
+}
+
+pub fn parse_url(url: &str) -> io::Result<(String, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only http:// URLs are supported",
+        )
+    })?;
+
+    let (authority, target) = match rest.find(['/', '?']) {
+        Some(i) if rest.as_bytes()[i] == b'?' => (&rest[..i], format!("/{}", &rest[i..])),
+        Some(i) => (&rest[..i], rest[i..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+    // 片段不会发送给服务器
+    let target = match target.split_once('#') {
+        Some((target, _)) => target.to_string(),
+        None => target,
+    };
+
+    if authority.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "URL without a host",
+        ));
+    }
+    let addr = if authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+    {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    Ok((addr, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urls() {
+        assert_eq!(
+            ("example.com:80".to_string(), "/".to_string()),
+            parse_url("http://example.com").unwrap()
+        );
+        assert_eq!(
+            ("127.0.0.1:7878".to_string(), "/a/b?x=1".to_string()),
+            parse_url("http://127.0.0.1:7878/a/b?x=1#top").unwrap()
+        );
+        assert_eq!(
+            ("localhost:80".to_string(), "/?q".to_string()),
+            parse_url("http://localhost?q").unwrap()
+        );
+        assert!(parse_url("https://example.com/").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::{fmt, time::Duration};
+
+use crate::{http::HttpDate, request::Request, response::Response};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+// Set-Cookie 中的一个 cookie 及其属性
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub path: Option<String>,
+    pub domain: Option<String>,
+    pub max_age: Option<Duration>,
+    pub expires: Option<HttpDate>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// 用于删除浏览器中已有 cookie 的 `Set-Cookie`。
+    pub fn removal(name: &str) -> Cookie {
+        Cookie::new(name, "")
+            .path("/")
+            .max_age(Duration::ZERO)
+            .expires(HttpDate::from(std::time::UNIX_EPOCH))
+    }
+
+    pub fn path(mut self, path: &str) -> Cookie {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Cookie {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Cookie {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn expires(mut self, expires: HttpDate) -> Cookie {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Cookie {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Cookie {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Cookie {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+// 生成 Set-Cookie 的值
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", expires)?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict"),
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax"),
+            Some(SameSite::None) => write!(f, "; SameSite=None"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 解析请求中的 `Cookie: a=1; b=2`。
+pub fn parse_cookies(header: &str) -> Vec<(String, String)> {
+    header
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| {
+            (
+                name.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            )
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+impl Request {
+    /// 返回名为 `name` 的 cookie 值，多个 `Cookie` header 都会查找。
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.headers
+            .get_all("Cookie")
+            .flat_map(parse_cookies)
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v)
+    }
+}
+
+impl Response {
+    pub fn with_cookie(mut self, cookie: &Cookie) -> Response {
+        self.headers.append("Set-Cookie", cookie.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_set_cookie() {
+        let cookie = Cookie::new("id", "abc")
+            .path("/")
+            .max_age(Duration::from_secs(60))
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax);
+        assert_eq!(
+            "id=abc; Path=/; Max-Age=60; Secure; HttpOnly; SameSite=Lax",
+            cookie.to_string()
+        );
+    }
+
+    #[test]
+    fn parses_cookie_header() {
+        let mut req = Request::new("GET", "/");
+        req.headers.append("Cookie", "a=1; b=\"two\"");
+        req.headers.append("Cookie", "c=3");
+
+        assert_eq!(Some("two".to_string()), req.cookie("b"));
+        assert_eq!(Some("3".to_string()), req.cookie("c"));
+        assert_eq!(None, req.cookie("d"));
+    }
+}
@@ -0,0 +1,197 @@
+use std::{io::Read, time::Duration};
+
+use crate::{request::Request, response::Response, server::Handler};
+
+// 跨域资源共享（CORS）配置，默认不允许任何来源
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: Vec<String>,
+    any_origin: bool,
+    methods: Vec<String>,
+    headers: Vec<String>,
+    expose_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Default for Cors {
+    fn default() -> Cors {
+        Cors::new()
+    }
+}
+
+impl Cors {
+    pub fn new() -> Cors {
+        Cors {
+            origins: Vec::new(),
+            any_origin: false,
+            methods: vec!["GET".to_string(), "HEAD".to_string(), "POST".to_string()],
+            headers: Vec::new(),
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// 允许来自 `origin` 的请求，例如 `https://app.example.com`，`*` 表示任意来源。
+    pub fn allow_origin(mut self, origin: &str) -> Cors {
+        if origin == "*" {
+            self.any_origin = true;
+        } else {
+            self.origins.push(origin.trim_end_matches('/').to_string());
+        }
+        self
+    }
+
+    /// 预检请求中允许的方法，默认是 GET、HEAD 和 POST。
+    pub fn allow_methods(mut self, methods: &[&str]) -> Cors {
+        self.methods = methods.iter().map(|m| m.to_ascii_uppercase()).collect();
+        self
+    }
+
+    /// 预检请求中允许的请求 header。
+    pub fn allow_headers(mut self, headers: &[&str]) -> Cors {
+        self.headers = headers.iter().map(|h| h.to_ascii_lowercase()).collect();
+        self
+    }
+
+    /// 允许浏览器中的脚本读取的响应 header。
+    pub fn expose_headers(mut self, headers: &[&str]) -> Cors {
+        self.expose_headers = headers.iter().map(|h| h.to_string()).collect();
+        self
+    }
+
+    /// 允许携带 cookie，此时不能用 `*` 允许任意来源，需要逐个列出。
+    pub fn allow_credentials(mut self, credentials: bool) -> Cors {
+        self.credentials = credentials;
+        self
+    }
+
+    /// 浏览器缓存预检结果的时间。
+    pub fn max_age(mut self, max_age: Duration) -> Cors {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn is_origin_allowed(&self, origin: &str) -> bool {
+        self.any_origin || self.origins.iter().any(|o| o.eq_ignore_ascii_case(origin))
+    }
+
+    /// 为 `handler` 加上 CORS 处理，预检请求直接在这里回复。
+    ///
+    /// # Panics
+    ///
+    /// 同时允许任意来源和携带 cookie 时会 panic，这等于让任何网站都能带着用户的 cookie 读取响应。
+    pub fn wrap<H: Handler>(self, handler: H) -> CorsHandler<H> {
+        assert!(
+            !(self.any_origin && self.credentials),
+            "CORS credentials can't be allowed for any origin"
+        );
+
+        CorsHandler {
+            cors: self,
+            handler,
+        }
+    }
+
+    fn is_preflight(req: &Request) -> bool {
+        req.method == "OPTIONS" && req.headers.contains("Access-Control-Request-Method")
+    }
+
+    fn respond(&self, req: &Request, handle: impl FnOnce() -> Response) -> Response {
+        let origin = match req.header("Origin") {
+            Some(origin) => origin,
+            // 同源请求或非浏览器请求
+            None => return handle(),
+        };
+
+        if Cors::is_preflight(req) {
+            return self.preflight(req, origin);
+        }
+
+        let response = handle();
+        self.apply(origin, response)
+    }
+
+    fn preflight(&self, req: &Request, origin: &str) -> Response {
+        let method = req
+            .header("Access-Control-Request-Method")
+            .unwrap_or("")
+            .trim()
+            .to_ascii_uppercase();
+        let headers_allowed = req
+            .header("Access-Control-Request-Headers")
+            .unwrap_or("")
+            .split(',')
+            .map(|h| h.trim().to_ascii_lowercase())
+            .filter(|h| !h.is_empty())
+            .all(|h| self.headers.contains(&h));
+
+        if !self.is_origin_allowed(origin) || !self.methods.contains(&method) || !headers_allowed {
+            return Response::text(403, "CORS preflight rejected\n").with_header("Vary", "Origin");
+        }
+
+        let mut response =
+            Response::new(204).with_header("Access-Control-Allow-Methods", self.methods.join(", "));
+        if !self.headers.is_empty() {
+            response =
+                response.with_header("Access-Control-Allow-Headers", self.headers.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            response =
+                response.with_header("Access-Control-Max-Age", max_age.as_secs().to_string());
+        }
+        self.apply(origin, response)
+    }
+
+    // 给允许的来源加上 Access-Control-Allow-* header
+    fn apply(&self, origin: &str, mut response: Response) -> Response {
+        // 响应随 Origin 变化，缓存需要区分
+        if !self.any_origin {
+            response.headers.append("Vary", "Origin");
+        }
+        if !self.is_origin_allowed(origin) {
+            return response;
+        }
+
+        let allow_origin = if self.any_origin { "*" } else { origin };
+        response
+            .headers
+            .insert("Access-Control-Allow-Origin", allow_origin);
+        if self.credentials {
+            response
+                .headers
+                .insert("Access-Control-Allow-Credentials", "true");
+        }
+        if !self.expose_headers.is_empty() {
+            response.headers.insert(
+                "Access-Control-Expose-Headers",
+                self.expose_headers.join(", "),
+            );
+        }
+        response
+    }
+}
+
+/// [`Cors::wrap`] 返回的处理器，请求 body 的读取方式由内层处理器决定。
+pub struct CorsHandler<H> {
+    cors: Cors,
+    handler: H,
+}
+
+impl<H: Handler> Handler for CorsHandler<H> {
+    fn handle(&self, req: &Request) -> Response {
+        self.cors.respond(req, || self.handler.handle(req))
+    }
+
+    fn streams_body(&self, req: &Request) -> bool {
+        // 预检请求在这里回复，不会用到 body
+        let preflight = req.headers.contains("Origin") && Cors::is_preflight(req);
+        !preflight && self.handler.streams_body(req)
+    }
+
+    fn handle_with_body(&self, req: &Request, body: &mut dyn Read) -> Response {
+        self.cors
+            .respond(req, || self.handler.handle_with_body(req, body))
+    }
+}
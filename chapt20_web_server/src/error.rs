@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+};
+
+use crate::{
+    extract::Rejection,
+    http::reason_phrase,
+    request::Request,
+    response::Response,
+    server::Handler,
+    template::{escape_html, TemplateError},
+};
+
+// 处理请求失败，status 是返回给客户端的状态码
+// 5xx 错误的 message 和 source 只写到日志里，不返回给客户端
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: u16,
+    pub message: String,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl HttpError {
+    pub fn new(status: u16, message: impl Into<String>) -> HttpError {
+        HttpError {
+            status,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn not_found() -> HttpError {
+        HttpError::new(404, "not found")
+    }
+
+    /// 服务器内部错误，`source` 会记录到日志中。
+    pub fn internal(source: impl Into<Box<dyn Error + Send + Sync>>) -> HttpError {
+        HttpError::new(500, "internal server error").with_source(source)
+    }
+
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> HttpError {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        self.status >= 500
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.status, self.message)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+/// I/O 错误对应的状态码：超时是 504，body 过大是 413，其余都是服务器的问题。
+pub fn io_status(e: &io::Error) -> u16 {
+    match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => 504,
+        io::ErrorKind::FileTooLarge => 413,
+        _ => 500,
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(e: io::Error) -> HttpError {
+        let status = io_status(&e);
+        HttpError::new(status, reason_phrase(status).to_ascii_lowercase()).with_source(e)
+    }
+}
+
+impl From<Rejection> for HttpError {
+    fn from(rejection: Rejection) -> HttpError {
+        HttpError::new(rejection.status, rejection.message)
+    }
+}
+
+impl From<TemplateError> for HttpError {
+    fn from(e: TemplateError) -> HttpError {
+        HttpError::internal(e)
+    }
+}
+
+impl From<serde_json::Error> for HttpError {
+    fn from(e: serde_json::Error) -> HttpError {
+        HttpError::internal(e)
+    }
+}
+
+// 按状态码配置的错误页面，没有配置或读取失败时使用内置页面
+#[derive(Debug, Clone, Default)]
+pub struct ErrorPages {
+    pages: HashMap<u16, PathBuf>,
+}
+
+impl ErrorPages {
+    pub fn new() -> ErrorPages {
+        ErrorPages::default()
+    }
+
+    /// 状态码为 `status` 时返回 `path` 文件的内容。
+    pub fn page(mut self, status: u16, path: impl Into<PathBuf>) -> ErrorPages {
+        self.pages.insert(status, path.into());
+        self
+    }
+
+    /// 状态码为 `status` 的错误页面，`message` 只在内置页面中显示。
+    pub fn render(&self, status: u16, message: &str) -> Response {
+        if let Some(path) = self.pages.get(&status) {
+            match fs::read(path) {
+                Ok(contents) => return Response::html(status, contents),
+                Err(e) => eprintln!("Failed to read error page {}: {}", path.display(), e),
+            }
+        }
+
+        let title = format!("{} {}", status, reason_phrase(status));
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+            title
+        );
+        if !message.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(message)));
+        }
+        html.push_str("</body>\n</html>\n");
+        Response::html(status, html)
+    }
+
+    /// 记录服务器错误的原因，返回对应的错误页面。
+    pub fn handle_error(&self, req: &Request, e: &HttpError) -> Response {
+        if e.is_server_error() {
+            eprintln!("{} {} failed: {}", req.method, req.path, e);
+            return self.render(e.status, "");
+        }
+        self.render(e.status, &e.message)
+    }
+
+    /// 把返回 `Result` 的处理函数包装成 `Handler`。
+    ///
+    /// 错误、panic 以及没有 body 的错误响应都会换成错误页面，
+    /// 原响应的 header（如 `Allow`、`Retry-After`）会保留。
+    pub fn wrap<F>(self, f: F) -> ErrorPagesHandler<F>
+    where
+        F: Fn(&Request) -> Result<Response, HttpError> + Send + Sync + 'static,
+    {
+        ErrorPagesHandler { pages: self, f }
+    }
+}
+
+/// [`ErrorPages::wrap`] 返回的处理器，处理函数收到的请求 body 已经读完。
+pub struct ErrorPagesHandler<F> {
+    pages: ErrorPages,
+    f: F,
+}
+
+impl<F> Handler for ErrorPagesHandler<F>
+where
+    F: Fn(&Request) -> Result<Response, HttpError> + Send + Sync + 'static,
+{
+    fn handle(&self, req: &Request) -> Response {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| (self.f)(req)))
+            .unwrap_or_else(|_| Err(HttpError::new(500, "handler panicked")));
+
+        match result {
+            Ok(response) if response.status >= 400 && response.body.is_empty() => {
+                let mut page = self.pages.render(response.status, "");
+                // 用 append 复制，多个 Set-Cookie 都要保留；类型和长度以页面为准
+                for (name, value) in response.headers.iter() {
+                    if !name.eq_ignore_ascii_case("Content-Length")
+                        && !name.eq_ignore_ascii_case("Content-Type")
+                    {
+                        page.headers.append(name, value);
+                    }
+                }
+                page
+            }
+            Ok(response) => response,
+            Err(e) => self.pages.handle_error(req, &e),
+        }
+    }
+}
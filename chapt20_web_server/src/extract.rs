@@ -0,0 +1,479 @@
+use std::{
+    env,
+    error::Error,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{request::Request, response::Response};
+
+// 各种 body 的默认大小上限
+pub const FORM_LIMIT: usize = 64 * 1024;
+pub const JSON_LIMIT: usize = 1024 * 1024;
+pub const MULTIPART_LIMIT: usize = 10 * 1024 * 1024;
+
+// 从请求中提取数据，失败时返回可以直接作为响应的 Rejection
+pub trait FromRequest: Sized {
+    fn from_request(req: &Request) -> Result<Self, Rejection>;
+}
+
+// 提取失败的原因，对应 400 / 413 / 415 响应
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub status: u16,
+    pub message: String,
+}
+
+impl Rejection {
+    pub fn bad_request(message: impl Into<String>) -> Rejection {
+        Rejection {
+            status: 400,
+            message: message.into(),
+        }
+    }
+
+    pub fn too_large(limit: usize) -> Rejection {
+        Rejection {
+            status: 413,
+            message: format!("request body is larger than {} bytes", limit),
+        }
+    }
+
+    pub fn unsupported(expected: &str) -> Rejection {
+        Rejection {
+            status: 415,
+            message: format!("expected Content-Type: {}", expected),
+        }
+    }
+
+    pub fn into_response(self) -> Response {
+        Response::text(self.status, format!("{}\n", self.message))
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.status, self.message)
+    }
+}
+
+impl Error for Rejection {}
+
+// 检查 Content-Type 和 body 大小
+fn check_body(req: &Request, content_type: &str, limit: usize) -> Result<(), Rejection> {
+    if !req
+        .content_type()
+        .is_some_and(|ct| ct.eq_ignore_ascii_case(content_type))
+    {
+        return Err(Rejection::unsupported(content_type));
+    }
+    if req.body.len() > limit {
+        return Err(Rejection::too_large(limit));
+    }
+    Ok(())
+}
+
+/// 解析 `a=1&b=2` 形式的字符串，`+` 解码为空格。
+pub fn parse_urlencoded(s: &str) -> Vec<(String, String)> {
+    s.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(name), decode_component(value))
+        })
+        .collect()
+}
+
+// 无法解码的 %XX 原样保留
+fn decode_component(s: &str) -> String {
+    let s = s.replace('+', " ");
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = s.get(i + 1..i + 3).filter(|_| bytes[i] == b'%');
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// 查询字符串参数
+#[derive(Debug, Clone, Default)]
+pub struct Query(pub Vec<(String, String)>);
+
+impl Query {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl FromRequest for Query {
+    fn from_request(req: &Request) -> Result<Query, Rejection> {
+        Ok(Query(parse_urlencoded(req.query.as_deref().unwrap_or(""))))
+    }
+}
+
+// application/x-www-form-urlencoded 表单
+#[derive(Debug, Clone, Default)]
+pub struct Form(pub Vec<(String, String)>);
+
+impl Form {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn from_request_with_limit(req: &Request, limit: usize) -> Result<Form, Rejection> {
+        check_body(req, "application/x-www-form-urlencoded", limit)?;
+        let body = std::str::from_utf8(&req.body)
+            .map_err(|_| Rejection::bad_request("form body is not valid UTF-8"))?;
+        Ok(Form(parse_urlencoded(body)))
+    }
+}
+
+impl FromRequest for Form {
+    fn from_request(req: &Request) -> Result<Form, Rejection> {
+        Form::from_request_with_limit(req, FORM_LIMIT)
+    }
+}
+
+// application/json，反序列化为 T
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> Json<T> {
+    pub fn from_request_with_limit(req: &Request, limit: usize) -> Result<Json<T>, Rejection> {
+        check_body(req, "application/json", limit)?;
+        serde_json::from_slice(&req.body)
+            .map(Json)
+            .map_err(|e| Rejection::bad_request(format!("invalid JSON: {}", e)))
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(req: &Request) -> Result<Json<T>, Rejection> {
+        Json::from_request_with_limit(req, JSON_LIMIT)
+    }
+}
+
+// multipart/form-data 中的文件，内容写在临时文件里
+// FilePart 被 drop 时删除临时文件，需要保留时用 persist 移走
+#[derive(Debug)]
+pub struct FilePart {
+    pub name: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size: u64,
+    path: PathBuf,
+}
+
+impl FilePart {
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// 把临时文件移动到 `dest`。
+    pub fn persist(self, dest: impl Into<PathBuf>) -> std::io::Result<()> {
+        let dest = dest.into();
+        if fs::rename(&self.path, &dest).is_err() {
+            // 跨文件系统时 rename 会失败，退回到复制
+            fs::copy(&self.path, &dest)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FilePart {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// multipart/form-data 表单，普通字段在内存中，文件写到临时目录
+#[derive(Debug, Default)]
+pub struct Multipart {
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<FilePart>,
+}
+
+impl Multipart {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn file(&self, name: &str) -> Option<&FilePart> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    pub fn from_request_with_limit(req: &Request, limit: usize) -> Result<Multipart, Rejection> {
+        Multipart::from_reader(req, &mut req.body.as_slice(), limit)
+    }
+
+    /// 从 `body` 中边读边解析，文件内容直接写到临时文件，不在内存中保留整个 body。
+    ///
+    /// 用在 [`Handler::handle_with_body`](crate::server::Handler::handle_with_body) 中时
+    /// `req.body` 为空，body 从 `body` 中读取。
+    ///
+    /// # Errors
+    ///
+    /// 读到的 body 超过 `limit` 时返回 413，格式错误时返回 400，写临时文件失败时返回 500。
+    pub fn from_reader(
+        req: &Request,
+        body: &mut dyn Read,
+        limit: usize,
+    ) -> Result<Multipart, Rejection> {
+        check_body(req, "multipart/form-data", limit)?;
+        if req.content_length().is_ok_and(|len| len > limit) {
+            return Err(Rejection::too_large(limit));
+        }
+
+        let boundary = req
+            .header("Content-Type")
+            .and_then(|ct| {
+                ct.split(';')
+                    .filter_map(|param| param.trim().split_once('='))
+                    .find(|(k, _)| k.eq_ignore_ascii_case("boundary"))
+                    .map(|(_, v)| v.trim_matches('"').to_string())
+            })
+            .filter(|b| !b.is_empty())
+            .ok_or_else(|| Rejection::bad_request("missing multipart boundary"))?;
+
+        let delimiter = format!("--{}", boundary).into_bytes();
+        let mut end_marker = b"\r\n".to_vec();
+        end_marker.extend_from_slice(&delimiter);
+
+        let mut parts = PartReader {
+            body,
+            buf: Vec::new(),
+            read: 0,
+            limit,
+        };
+        // 第一个分隔符之前的内容直接丢弃
+        parts.copy_until(&delimiter, &mut io::sink())?;
+
+        let mut multipart = Multipart::default();
+        loop {
+            if !parts.fill_to(2)? {
+                return Err(malformed());
+            }
+            // 结束分隔符 --boundary--
+            if parts.buf.starts_with(b"--") {
+                return Ok(multipart);
+            }
+            if !parts.buf.starts_with(b"\r\n") {
+                return Err(malformed());
+            }
+            parts.buf.drain(..2);
+
+            let mut head = Vec::new();
+            parts.copy_until(b"\r\n\r\n", &mut head)?;
+            let head = String::from_utf8(head).map_err(|_| malformed())?;
+            let (name, filename, content_type) = parse_part_head(&head)?;
+
+            match filename {
+                Some(filename) => {
+                    let file = spool(name, filename, content_type, |file| {
+                        parts.copy_until(&end_marker, file)
+                    })?;
+                    multipart.files.push(file);
+                }
+                None => {
+                    let mut value = Vec::new();
+                    parts.copy_until(&end_marker, &mut value)?;
+                    let value = String::from_utf8_lossy(&value).into_owned();
+                    multipart.fields.push((name, value));
+                }
+            }
+        }
+    }
+}
+
+impl FromRequest for Multipart {
+    fn from_request(req: &Request) -> Result<Multipart, Rejection> {
+        Multipart::from_request_with_limit(req, MULTIPART_LIMIT)
+    }
+}
+
+fn malformed() -> Rejection {
+    Rejection::bad_request("malformed multipart body")
+}
+
+// 返回 name、filename 和 Content-Type
+fn parse_part_head(head: &str) -> Result<(String, Option<String>, Option<String>), Rejection> {
+    let mut name = None;
+    let mut filename = None;
+    let mut content_type = None;
+
+    for line in head.split("\r\n") {
+        let (header, value) = match line.split_once(':') {
+            Some(header) => header,
+            None => continue,
+        };
+        if header.trim().eq_ignore_ascii_case("Content-Type") {
+            content_type = Some(value.trim().to_string());
+        } else if header.trim().eq_ignore_ascii_case("Content-Disposition") {
+            for param in value.split(';').skip(1) {
+                if let Some((k, v)) = param.trim().split_once('=') {
+                    let v = v.trim_matches('"').to_string();
+                    match k.trim() {
+                        "name" => name = Some(v),
+                        "filename" => filename = Some(v),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    let name = name.ok_or_else(|| Rejection::bad_request("multipart part without a name"))?;
+    Ok((name, filename, content_type))
+}
+
+// 逐块读取 multipart body，缓冲区里只保留还没处理的数据
+struct PartReader<'a> {
+    body: &'a mut dyn Read,
+    buf: Vec<u8>,
+    read: usize,
+    limit: usize,
+}
+
+impl PartReader<'_> {
+    // 再读一块数据，body 结束时返回 false
+    fn fill(&mut self) -> Result<bool, Rejection> {
+        let mut chunk = [0; 8192];
+        let n = loop {
+            match self.body.read(&mut chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == io::ErrorKind::FileTooLarge => {
+                    return Err(Rejection::too_large(self.limit))
+                }
+                Err(e) => {
+                    return Err(Rejection::bad_request(format!(
+                        "failed to read multipart body: {}",
+                        e
+                    )))
+                }
+            }
+        };
+        self.read += n;
+        if self.read > self.limit {
+            return Err(Rejection::too_large(self.limit));
+        }
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+
+    // 缓冲区里至少有 n 个字节时返回 true
+    fn fill_to(&mut self, n: usize) -> Result<bool, Rejection> {
+        while self.buf.len() < n {
+            if !self.fill()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // 把 marker 之前的内容写到 out 并跳过 marker，返回写入的字节数
+    fn copy_until(&mut self, marker: &[u8], out: &mut dyn Write) -> Result<u64, Rejection> {
+        let mut written = 0;
+        loop {
+            if let Some(i) = find(&self.buf, marker) {
+                write_part(out, &self.buf[..i])?;
+                self.buf.drain(..i + marker.len());
+                return Ok(written + i as u64);
+            }
+            // 末尾可能是 marker 的前半部分，先留在缓冲区里
+            let safe = self.buf.len().saturating_sub(marker.len() - 1);
+            write_part(out, &self.buf[..safe])?;
+            self.buf.drain(..safe);
+            written += safe as u64;
+
+            if !self.fill()? {
+                return Err(malformed());
+            }
+        }
+    }
+}
+
+fn write_part(out: &mut dyn Write, data: &[u8]) -> Result<(), Rejection> {
+    out.write_all(data).map_err(|e| {
+        eprintln!("Failed to spool upload: {}", e);
+        Rejection {
+            status: 500,
+            message: "failed to store upload".to_string(),
+        }
+    })
+}
+
+// 把上传的文件写到临时目录，文件名带随机数，不会被提前猜到
+fn spool(
+    name: String,
+    filename: String,
+    content_type: Option<String>,
+    copy: impl FnOnce(&mut File) -> Result<u64, Rejection>,
+) -> Result<FilePart, Rejection> {
+    let path = env::temp_dir().join(format!("upload-{:032x}", rand::random::<u128>()));
+
+    // create_new 在路径已存在（包括符号链接）时失败，不会写到别处
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&path).map_err(|e| {
+        eprintln!("Failed to spool upload to {}: {}", path.display(), e);
+        Rejection {
+            status: 500,
+            message: "failed to store upload".to_string(),
+        }
+    })?;
+    let size = match copy(&mut file) {
+        Ok(size) => size,
+        Err(rejection) => {
+            drop(file);
+            let _ = fs::remove_file(&path);
+            return Err(rejection);
+        }
+    };
+
+    Ok(FilePart {
+        name,
+        filename,
+        content_type,
+        size,
+        path,
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,414 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Read},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// 请求头或响应头的最大字节数，防止对方发送无穷无尽的 header
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+// 请求和响应共用的 header 集合
+// header 名大小写不敏感，保留插入顺序，同名 header 可以出现多次
+#[derive(Debug, Clone, Default)]
+pub struct Headers {
+    entries: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub fn new() -> Headers {
+        Headers {
+            entries: Vec::new(),
+        }
+    }
+
+    /// 返回第一个名为 `name` 的 header 值。
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 返回所有名为 `name` 的 header 值。
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// 设置 header，会替换掉已有的同名 header。
+    pub fn insert(&mut self, name: &str, value: impl Into<String>) {
+        self.remove(name);
+        self.entries.push((name.to_string(), value.into()));
+    }
+
+    /// 追加 header，不影响已有的同名 header（如 `Set-Cookie`）。
+    pub fn append(&mut self, name: &str, value: impl Into<String>) {
+        self.entries.push((name.to_string(), value.into()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// 状态码对应的原因短语
+pub fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+// 解码 URL 中的 %XX，结果不是合法 UTF-8 时返回 None
+pub fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// 读取起始行（请求行或状态行）和 header。
+///
+/// 对方在发送任何数据前关闭连接时返回 `Ok(None)`。
+pub(crate) fn read_head(reader: &mut impl BufRead) -> io::Result<Option<(String, Headers)>> {
+    let mut head_size = 0;
+
+    let start_line = match read_line(reader, &mut head_size)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+
+    let mut headers = Headers::new();
+    loop {
+        let line = match read_line(reader, &mut head_size)? {
+            Some(line) => line,
+            None => return Err(invalid_data("unexpected end of headers")),
+        };
+        if line.is_empty() {
+            break;
+        }
+
+        match line.split_once(':') {
+            Some((name, value)) if !name.is_empty() && !name.contains(' ') => {
+                headers.append(name, value.trim());
+            }
+            _ => return Err(invalid_data("malformed header line")),
+        }
+    }
+
+    Ok(Some((start_line, headers)))
+}
+
+// 读取一行并去掉结尾的 CRLF，EOF 时返回 None
+fn read_line(reader: &mut impl BufRead, head_size: &mut usize) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    let limit = (MAX_HEAD_SIZE - *head_size) as u64 + 1;
+    let n = reader.by_ref().take(limit).read_line(&mut line)?;
+    if n == 0 {
+        return Ok(None);
+    }
+
+    *head_size += n;
+    if *head_size > MAX_HEAD_SIZE {
+        return Err(invalid_data("message head too large"));
+    }
+
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+pub(crate) fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 读取 `Transfer-Encoding: chunked` 编码的 body。
+///
+/// # Errors
+///
+/// 解码后的长度超过 `limit` 时返回 `FileTooLarge`，格式错误时返回 `InvalidData`。
+pub fn read_chunked(reader: &mut impl BufRead, limit: usize) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    ChunkedReader::new(reader, limit).read_to_end(&mut body)?;
+    Ok(body)
+}
+
+// 边读边解码 chunked 编码的 body，读到最后一个 chunk 和 trailer 后返回 EOF
+pub struct ChunkedReader<R> {
+    reader: R,
+    limit: usize,
+    // 已经解码的字节数
+    total: usize,
+    // 当前 chunk 还没有读取的字节数
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    /// 解码后的长度超过 `limit` 时读取返回 `FileTooLarge`。
+    pub fn new(reader: R, limit: usize) -> ChunkedReader<R> {
+        ChunkedReader {
+            reader,
+            limit,
+            total: 0,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    // chunk-size [; chunk-ext] CRLF
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let mut line = String::new();
+        self.reader.by_ref().take(1024).read_line(&mut line)?;
+        let size = line.trim_end().split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid_data("bad chunk size"))?;
+
+        if size == 0 {
+            // 跳过 trailer，直到空行
+            loop {
+                let mut line = String::new();
+                let n = self.reader.by_ref().take(8 * 1024).read_line(&mut line)?;
+                if n == 0 || line.trim_end().is_empty() {
+                    break;
+                }
+            }
+            self.done = true;
+            return Ok(());
+        }
+
+        // size 来自对方，直接相加可能溢出
+        self.total = self
+            .total
+            .checked_add(size)
+            .ok_or_else(|| invalid_data("chunk size too large"))?;
+        if self.total > self.limit {
+            return Err(io::Error::from(io::ErrorKind::FileTooLarge));
+        }
+        self.remaining = size;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 && !self.done {
+            self.next_chunk()?;
+        }
+        if self.done {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(self.remaining);
+        let n = self.reader.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        self.remaining -= n;
+        if self.remaining == 0 {
+            let mut crlf = [0; 2];
+            self.reader.read_exact(&mut crlf)?;
+            if &crlf != b"\r\n" {
+                return Err(invalid_data("bad chunk end"));
+            }
+        }
+        Ok(n)
+    }
+}
+
+// HTTP 日期（IMF-fixdate），例如 `Sun, 06 Nov 1994 08:49:37 GMT`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HttpDate {
+    secs: u64,
+}
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl HttpDate {
+    pub fn now() -> HttpDate {
+        HttpDate::from(SystemTime::now())
+    }
+
+    /// 解析 IMF-fixdate 格式的日期，其他格式返回 `None`。
+    pub fn parse(s: &str) -> Option<HttpDate> {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        let s = s.trim();
+        let (_, rest) = s.split_once(", ")?;
+        let parts: Vec<&str> = rest.split(' ').collect();
+        if parts.len() != 5 || parts[4] != "GMT" {
+            return None;
+        }
+
+        let day: u64 = parts[0].parse().ok()?;
+        let month = MONTHS.iter().position(|m| *m == parts[1])? as u64 + 1;
+        let year: u64 = parts[2].parse().ok()?;
+
+        let time: Vec<u64> = parts[3]
+            .split(':')
+            .map(|p| p.parse().ok())
+            .collect::<Option<_>>()?;
+        if time.len() != 3 || time[0] > 23 || time[1] > 59 || time[2] > 60 {
+            return None;
+        }
+        if year < 1970 || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        let days = days_from_civil(year, month, day);
+        Some(HttpDate {
+            secs: days * 86400 + time[0] * 3600 + time[1] * 60 + time[2],
+        })
+    }
+
+    pub fn as_secs(&self) -> u64 {
+        self.secs
+    }
+}
+
+impl From<SystemTime> for HttpDate {
+    // HTTP 日期只精确到秒
+    fn from(time: SystemTime) -> HttpDate {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        HttpDate { secs }
+    }
+}
+
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.secs / 86400;
+        let secs_of_day = self.secs % 86400;
+        let (year, month, day) = civil_from_days(days);
+
+        write!(
+            f,
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            DAYS[(days % 7) as usize],
+            day,
+            MONTHS[(month - 1) as usize],
+            year,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60
+        )
+    }
+}
+
+// 公历日期与 1970-01-01 起天数的互相转换
+// 算法来自 Howard Hinnant 的 chrono-Compatible Low-Level Date Algorithms
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn http_date_round_trip() {
+        let date = HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(784111777, date.as_secs());
+        assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT", date.to_string());
+    }
+
+    #[test]
+    fn chunk_size_overflow_is_rejected() {
+        let mut input = Cursor::new(b"1\r\na\r\nFFFFFFFFFFFFFFFF\r\n".to_vec());
+        let err = read_chunked(&mut input, 1024).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+        let mut input = Cursor::new(b"1\r\na\r\n400\r\n".to_vec());
+        let err = read_chunked(&mut input, 1024).unwrap_err();
+        assert_eq!(io::ErrorKind::FileTooLarge, err.kind());
+    }
+
+    #[test]
+    fn headers_are_case_insensitive() {
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", "text/html");
+        headers.insert("content-type", "text/plain");
+
+        assert_eq!(1, headers.len());
+        assert_eq!(Some("text/plain"), headers.get("CONTENT-TYPE"));
+    }
+}
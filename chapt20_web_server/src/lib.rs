@@ -1,11 +1,37 @@
+pub mod cgi;
+pub mod client;
+pub mod cookie;
+pub mod cors;
+pub mod error;
+pub mod extract;
+pub mod http;
+pub mod limit;
+pub mod proxy;
+pub mod range;
+pub mod request;
+pub mod response;
+pub mod security;
+pub mod server;
+pub mod session;
+pub mod sse;
+pub mod static_files;
+pub mod template;
+pub mod vhost;
+pub mod websocket;
+
 use std::{
-    sync::{mpsc, Arc, Mutex},
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
 };
 
 pub struct ThreadPool {
     workers: Vec<Worker>,
     sender: Option<mpsc::Sender<Job>>,
+    shutdown: Shutdown,
 }
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
@@ -29,13 +55,15 @@ impl ThreadPool {
 
         let mut workers = Vec::with_capacity(size);
 
+        let shutdown = Shutdown::default();
         for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+            workers.push(Worker::new(id, Arc::clone(&receiver), shutdown.clone()));
         }
 
         ThreadPool {
             workers,
             sender: Some(sender),
+            shutdown,
         }
     }
 
@@ -51,6 +79,8 @@ impl ThreadPool {
 // 为 ThreadPool 实现 Drop Trait
 impl Drop for ThreadPool {
     fn drop(&mut self) {
+        // 先通知长时间运行的任务（如 SSE）结束，否则下面的 join 会一直等待
+        self.shutdown.trigger();
         drop(self.sender.take());
 
         for worker in &mut self.workers {
@@ -70,17 +100,20 @@ struct Worker {
 }
 
 impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv();
-            match message {
-                Ok(job) => {
-                    println!("Worker {} got a job; executing.", id);
-                    job();
-                }
-                Err(_) => {
-                    println!("Worker {} disconnected; shutting down.", id);
-                    break;
+    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>, shutdown: Shutdown) -> Worker {
+        let thread = thread::spawn(move || {
+            CURRENT_SHUTDOWN.with(|current| *current.borrow_mut() = Some(shutdown));
+            loop {
+                let message = receiver.lock().unwrap().recv();
+                match message {
+                    Ok(job) => {
+                        println!("Worker {} got a job; executing.", id);
+                        job();
+                    }
+                    Err(_) => {
+                        println!("Worker {} disconnected; shutting down.", id);
+                        break;
+                    }
                 }
             }
         });
@@ -90,3 +123,26 @@ impl Worker {
         }
     }
 }
+
+thread_local! {
+    static CURRENT_SHUTDOWN: RefCell<Option<Shutdown>> = const { RefCell::new(None) };
+}
+
+// 线程池关闭的信号，长时间运行的任务需要定期检查并尽快结束
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown(Arc<AtomicBool>);
+
+impl Shutdown {
+    /// 当前线程所在线程池的关闭信号，不在线程池中时返回一个永远不会触发的信号。
+    pub fn current() -> Shutdown {
+        CURRENT_SHUTDOWN.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::response::Response;
+
+// 令牌桶：每秒补充 per_second 个令牌，最多积攒 burst 个
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+// 每个 IP 的连接数和令牌桶
+#[derive(Debug)]
+struct IpState {
+    connections: usize,
+    tokens: f64,
+    updated: Instant,
+}
+
+// 清理空闲 IP 的间隔，清理需要遍历所有记录，不在每次释放连接时进行
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+// 按 IP 记录的状态
+#[derive(Debug, Default)]
+struct IpTable {
+    states: HashMap<IpAddr, IpState>,
+    pruned: Option<Instant>,
+}
+
+// 连接和请求的限制，按 peer_addr 的 IP 计算
+// 所有限制默认关闭
+#[derive(Debug, Default)]
+pub struct Limiter {
+    max_connections: Option<usize>,
+    max_connections_per_ip: Option<usize>,
+    rate_limit: Option<RateLimit>,
+    ips: Mutex<IpTable>,
+    active: AtomicUsize,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    rate_limited: AtomicU64,
+}
+
+// 限制的配置和计数，用于观察服务器状态
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitStats {
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub rate_limit: Option<RateLimit>,
+    pub active_connections: usize,
+    pub tracked_ips: usize,
+    pub accepted_connections: u64,
+    pub rejected_connections: u64,
+    pub rate_limited_requests: u64,
+}
+
+// 被拒绝的原因，客户端在 retry_after 之后可以重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limited {
+    pub retry_after: Duration,
+}
+
+impl Limited {
+    /// 429 响应，`Retry-After` 向上取整到秒。
+    pub fn into_response(self) -> Response {
+        let secs = self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+        Response::text(429, "Too Many Requests\n")
+            .with_header("Retry-After", secs.max(1).to_string())
+            .with_header("Connection", "close")
+    }
+}
+
+impl Limiter {
+    pub fn new() -> Limiter {
+        Limiter::default()
+    }
+
+    /// 服务器同时处理的连接总数上限，包括在线程池中排队的连接。
+    pub fn max_connections(mut self, max: usize) -> Limiter {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// 每个 IP 同时打开的连接数上限。
+    ///
+    /// 每个连接占用一个工作线程，上限小于线程数时一个 IP 才不能占满线程池。
+    pub fn max_connections_per_ip(mut self, max: usize) -> Limiter {
+        self.max_connections_per_ip = Some(max);
+        self
+    }
+
+    /// 每个 IP 每秒最多 `per_second` 个请求，允许突发 `burst` 个。
+    ///
+    /// # Panics
+    ///
+    /// `per_second` 不是正数或 `burst` 为 0 时会 panic。
+    pub fn rate_limit(mut self, per_second: f64, burst: u32) -> Limiter {
+        assert!(per_second > 0.0 && burst > 0);
+        self.rate_limit = Some(RateLimit { per_second, burst });
+        self
+    }
+
+    /// 接受一个新连接，返回的 guard 被 drop 时释放连接计数。
+    ///
+    /// # Errors
+    ///
+    /// 超过总连接数或单个 IP 的连接数时返回 `Limited`。
+    pub fn acquire(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionGuard, Limited> {
+        let mut ips = self.ips.lock().unwrap();
+        self.prune(&mut ips, Instant::now());
+
+        let over_global = self
+            .max_connections
+            .is_some_and(|max| self.active.load(Ordering::Relaxed) >= max);
+        let over_ip = self.max_connections_per_ip.is_some_and(|max| {
+            ips.states
+                .get(&ip)
+                .is_some_and(|state| state.connections >= max)
+        });
+        if over_global || over_ip {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(Limited {
+                retry_after: Duration::from_secs(1),
+            });
+        }
+
+        self.state(&mut ips, ip).connections += 1;
+        self.active.fetch_add(1, Ordering::Relaxed);
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+        Ok(ConnectionGuard {
+            limiter: Arc::clone(self),
+            ip,
+        })
+    }
+
+    /// 为一个请求从 `ip` 的令牌桶中取出一个令牌。
+    ///
+    /// # Errors
+    ///
+    /// 令牌用完时返回 `Limited`，其中是下一个令牌补充所需的时间。
+    pub fn check_rate(&self, ip: IpAddr) -> Result<(), Limited> {
+        let rate = match self.rate_limit {
+            Some(rate) => rate,
+            None => return Ok(()),
+        };
+
+        let mut ips = self.ips.lock().unwrap();
+        let state = self.state(&mut ips, ip);
+        refill(state, rate, Instant::now());
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            return Ok(());
+        }
+
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+        Err(Limited {
+            retry_after: Duration::from_secs_f64((1.0 - state.tokens) / rate.per_second),
+        })
+    }
+
+    pub fn stats(&self) -> LimitStats {
+        let tracked_ips = self.ips.lock().unwrap().states.len();
+        LimitStats {
+            max_connections: self.max_connections,
+            max_connections_per_ip: self.max_connections_per_ip,
+            rate_limit: self.rate_limit,
+            active_connections: self.active.load(Ordering::Relaxed),
+            tracked_ips,
+            accepted_connections: self.accepted.load(Ordering::Relaxed),
+            rejected_connections: self.rejected.load(Ordering::Relaxed),
+            rate_limited_requests: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+
+    fn state<'a>(&self, ips: &'a mut IpTable, ip: IpAddr) -> &'a mut IpState {
+        let burst = self.rate_limit.map_or(0.0, |rate| f64::from(rate.burst));
+        ips.states.entry(ip).or_insert_with(|| IpState {
+            connections: 0,
+            tokens: burst,
+            updated: Instant::now(),
+        })
+    }
+
+    fn release(&self, ip: IpAddr) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+
+        let mut ips = self.ips.lock().unwrap();
+        let now = Instant::now();
+        let idle = ips.states.get_mut(&ip).is_some_and(|state| {
+            state.connections -= 1;
+            self.is_idle(state, now)
+        });
+        if idle {
+            ips.states.remove(&ip);
+        }
+    }
+
+    // 令牌桶还没满的 IP 在释放时留下，之后接受连接时每隔 PRUNE_INTERVAL 清理一次
+    fn prune(&self, ips: &mut IpTable, now: Instant) {
+        if ips
+            .pruned
+            .is_some_and(|pruned| now.duration_since(pruned) < PRUNE_INTERVAL)
+        {
+            return;
+        }
+        ips.pruned = Some(now);
+        ips.states.retain(|_, state| !self.is_idle(state, now));
+    }
+
+    // 没有连接且令牌桶已满的 IP 不需要再记录
+    fn is_idle(&self, state: &mut IpState, now: Instant) -> bool {
+        if state.connections > 0 {
+            return false;
+        }
+        match self.rate_limit {
+            Some(rate) => {
+                refill(state, rate, now);
+                state.tokens >= f64::from(rate.burst)
+            }
+            None => true,
+        }
+    }
+}
+
+fn refill(state: &mut IpState, rate: RateLimit, now: Instant) {
+    let elapsed = now.duration_since(state.updated).as_secs_f64();
+    state.tokens = (state.tokens + elapsed * rate.per_second).min(f64::from(rate.burst));
+    state.updated = now;
+}
+
+// 一个已接受的连接，drop 时归还连接计数
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    limiter: Arc<Limiter>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.limiter.release(self.ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn limits_connections_per_ip() {
+        let limiter = Arc::new(Limiter::new().max_connections_per_ip(2));
+
+        let first = limiter.acquire(IP).unwrap();
+        let _second = limiter.acquire(IP).unwrap();
+        assert!(limiter.acquire(IP).is_err());
+
+        drop(first);
+        assert!(limiter.acquire(IP).is_ok());
+        assert_eq!(1, limiter.stats().rejected_connections);
+    }
+
+    #[test]
+    fn token_bucket_allows_burst_then_limits() {
+        let limiter = Limiter::new().rate_limit(1.0, 3);
+
+        for _ in 0..3 {
+            assert!(limiter.check_rate(IP).is_ok());
+        }
+        let limited = limiter.check_rate(IP).unwrap_err();
+        assert!(limited.retry_after <= Duration::from_secs(1));
+        assert_eq!(
+            Some("1"),
+            limited.into_response().headers.get("Retry-After")
+        );
+    }
+
+    #[test]
+    fn prunes_idle_ips_lazily() {
+        let limiter = Arc::new(Limiter::new().rate_limit(1000.0, 1));
+
+        drop(limiter.acquire(IP).unwrap());
+        assert_eq!(0, limiter.stats().tracked_ips);
+
+        // 令牌桶没满时释放连接后仍然保留
+        let guard = limiter.acquire(IP).unwrap();
+        limiter.check_rate(IP).unwrap();
+        drop(guard);
+        assert_eq!(1, limiter.stats().tracked_ips);
+
+        let mut ips = limiter.ips.lock().unwrap();
+        let now = Instant::now();
+        limiter.prune(&mut ips, now);
+        assert_eq!(1, ips.states.len());
+        limiter.prune(&mut ips, now + PRUNE_INTERVAL);
+        assert!(ips.states.is_empty());
+    }
+}
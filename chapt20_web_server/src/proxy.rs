@@ -0,0 +1,397 @@
+use std::{
+    io::{self, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    request::Request,
+    response::{Body, Response},
+    server::Handler,
+};
+
+// 逐跳（hop-by-hop）header 只对一条连接有效，不能转发
+const HOP_BY_HOP: [&str; 8] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+// 一个上游服务，连续失败后会被暂时标记为不可用（被动健康检查）
+struct Upstream {
+    addr: String,
+    health: Mutex<Health>,
+}
+
+#[derive(Default)]
+struct Health {
+    fails: u32,
+    down_until: Option<Instant>,
+}
+
+impl Upstream {
+    fn is_available(&self, now: Instant) -> bool {
+        match self.health.lock().unwrap().down_until {
+            Some(until) => now >= until,
+            None => true,
+        }
+    }
+
+    fn mark_success(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.fails = 0;
+        health.down_until = None;
+    }
+
+    fn mark_failure(&self, max_fails: u32, fail_timeout: Duration) {
+        let mut health = self.health.lock().unwrap();
+        health.fails += 1;
+        if health.fails >= max_fails {
+            eprintln!("Upstream {} marked down", self.addr);
+            health.down_until = Some(Instant::now() + fail_timeout);
+        }
+    }
+}
+
+// 反向代理：把匹配前缀的请求轮流转发给多个上游
+pub struct Proxy {
+    prefix: String,
+    strip_prefix: bool,
+    upstreams: Vec<Upstream>,
+    next: AtomicUsize,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    max_fails: u32,
+    fail_timeout: Duration,
+}
+
+impl Proxy {
+    /// 把路径以 `prefix` 开头的请求转发到 `upstreams`（`host:port`）。
+    ///
+    /// # Panics
+    ///
+    /// `upstreams` 为空时会 panic。
+    pub fn new(prefix: &str, upstreams: &[&str]) -> Proxy {
+        assert!(!upstreams.is_empty());
+
+        Proxy {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            strip_prefix: false,
+            upstreams: upstreams
+                .iter()
+                .map(|addr| Upstream {
+                    addr: addr.to_string(),
+                    health: Mutex::new(Health::default()),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(30),
+            max_fails: 1,
+            fail_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// 转发前去掉路径前缀，`/legacy/a` 转发为 `/a`。
+    pub fn strip_prefix(mut self, strip: bool) -> Proxy {
+        self.strip_prefix = strip;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Proxy {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> Proxy {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// 连续失败 `max_fails` 次后，上游在 `fail_timeout` 内不再被选中。
+    pub fn health(mut self, max_fails: u32, fail_timeout: Duration) -> Proxy {
+        self.max_fails = max_fails.max(1);
+        self.fail_timeout = fail_timeout;
+        self
+    }
+
+    pub fn matches(&self, req: &Request) -> bool {
+        match req.path.strip_prefix(&self.prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/') || self.prefix.is_empty(),
+            None => false,
+        }
+    }
+
+    pub fn forward(&self, req: &Request) -> Response {
+        self.forward_with(req, None)
+    }
+
+    // body 为 Some 时边从客户端读取边转发，否则转发 req.body
+    fn forward_with(&self, req: &Request, mut body: Option<&mut dyn Read>) -> Response {
+        // 轮询：从 next 开始依次尝试，跳过被标记为不可用的上游
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let count = self.upstreams.len();
+        let mut order: Vec<&Upstream> = (0..count)
+            .map(|i| &self.upstreams[(start + i) % count])
+            .filter(|upstream| upstream.is_available(now))
+            .collect();
+        // 全部不可用时仍然尝试一次，而不是直接失败
+        if order.is_empty() {
+            order.push(&self.upstreams[start % count]);
+        }
+
+        let mut last_error = None;
+        for upstream in order {
+            match self.connect(upstream) {
+                Ok(stream) => {
+                    return match self.exchange(stream, upstream, req, body.take()) {
+                        Ok(response) => {
+                            upstream.mark_success();
+                            response
+                        }
+                        // 客户端发送的 body 有问题，不是上游的错误
+                        Err(Failure::Client(e)) => client_error_response(&e),
+                        Err(Failure::Upstream(e)) => {
+                            eprintln!("Upstream {} failed: {}", upstream.addr, e);
+                            upstream.mark_failure(self.max_fails, self.fail_timeout);
+                            error_response(&e)
+                        }
+                    };
+                }
+                // 连接失败时请求还没有发出，可以安全地换下一个上游重试
+                Err(e) => {
+                    eprintln!("Failed to connect to upstream {}: {}", upstream.addr, e);
+                    upstream.mark_failure(self.max_fails, self.fail_timeout);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        error_response(&last_error.unwrap())
+    }
+
+    fn connect(&self, upstream: &Upstream) -> io::Result<TcpStream> {
+        let mut last_error = io::Error::from(io::ErrorKind::AddrNotAvailable);
+        for addr in upstream.addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.connect_timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.read_timeout))?;
+                    stream.set_write_timeout(Some(self.read_timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    // 发送请求，读取响应头，body 在写给客户端时再从上游读取
+    fn exchange(
+        &self,
+        mut stream: TcpStream,
+        upstream: &Upstream,
+        req: &Request,
+        body: Option<&mut dyn Read>,
+    ) -> Result<Response, Failure> {
+        // 长度未知的流式 body 重新按 chunked 编码发送
+        let len = match &body {
+            None => Some(req.body.len()),
+            Some(_) if req.is_chunked() => None,
+            Some(_) => Some(req.content_length().map_err(Failure::Client)?),
+        };
+        stream
+            .write_all(&self.upstream_request(req, upstream, len))
+            .map_err(Failure::Upstream)?;
+        match body {
+            Some(body) => send_body(&mut stream, body, len)?,
+            None => stream.write_all(&req.body).map_err(Failure::Upstream)?,
+        }
+        stream.flush().map_err(Failure::Upstream)?;
+
+        read_response(stream, req).map_err(Failure::Upstream)
+    }
+
+    // len 为 None 时 body 按 chunked 编码发送
+    fn upstream_request(&self, req: &Request, upstream: &Upstream, len: Option<usize>) -> Vec<u8> {
+        let mut path = req.path.as_str();
+        if self.strip_prefix {
+            path = path.strip_prefix(&self.prefix).unwrap_or(path);
+            if path.is_empty() {
+                path = "/";
+            }
+        }
+
+        let mut head = format!("{} {}", req.method, path);
+        if let Some(query) = &req.query {
+            head.push('?');
+            head.push_str(query);
+        }
+        head.push_str(" HTTP/1.1\r\n");
+
+        let mut headers = req.headers.clone();
+        // Connection 中列出的 header 也是逐跳的
+        if let Some(connection) = req.header("Connection") {
+            for name in connection.split(',') {
+                headers.remove(name.trim());
+            }
+        }
+        for name in HOP_BY_HOP {
+            headers.remove(name);
+        }
+        // 100-continue 已经由本服务器回复，上游不需要再等待
+        headers.remove("Expect");
+
+        if let Some(host) = req.header("Host") {
+            headers.insert("X-Forwarded-Host", host);
+        }
+        headers.insert("Host", upstream.addr.clone());
+        if let Some(peer) = req.peer_addr {
+            let forwarded_for = match req.header("X-Forwarded-For") {
+                Some(previous) => format!("{}, {}", previous, peer.ip()),
+                None => peer.ip().to_string(),
+            };
+            headers.insert("X-Forwarded-For", forwarded_for);
+        }
+        headers.insert("X-Forwarded-Proto", "http");
+        headers.insert("Connection", "close");
+        headers.remove("Content-Length");
+        match len {
+            None => headers.insert("Transfer-Encoding", "chunked"),
+            Some(len) if len > 0 || matches!(req.method.as_str(), "POST" | "PUT" | "PATCH") => {
+                headers.insert("Content-Length", len.to_string())
+            }
+            Some(_) => {}
+        }
+
+        for (name, value) in headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+}
+
+// 读取上游的响应头
+fn read_response(stream: TcpStream, req: &Request) -> io::Result<Response> {
+    let mut reader = BufReader::new(stream);
+    // 跳过 100 Continue 等中间响应，101 除外
+    let mut response = Response::read_head(&mut reader)?;
+    while matches!(response.status, 100 | 102..=199) {
+        response = Response::read_head(&mut reader)?;
+    }
+
+    let bodiless = req.is_head() || matches!(response.status, 100..=199 | 204 | 304);
+    let chunked = response
+        .headers
+        .get("Transfer-Encoding")
+        .is_some_and(|te| te.to_ascii_lowercase().contains("chunked"));
+    let len = response
+        .headers
+        .get("Content-Length")
+        .and_then(|len| len.trim().parse::<u64>().ok());
+
+    for name in HOP_BY_HOP {
+        // chunked body 原样转发，保留 Transfer-Encoding
+        if !(chunked && name == "Transfer-Encoding") {
+            response.headers.remove(name);
+        }
+    }
+
+    response.body = match (bodiless, chunked, len) {
+        (true, _, _) => Body::Empty,
+        (false, true, _) => Body::stream(reader, None),
+        (false, false, Some(len)) => Body::stream(reader.take(len), Some(len)),
+        // 既没有长度也不是 chunked，body 一直到上游关闭连接
+        (false, false, None) => Body::stream(reader, None),
+    };
+    Ok(response)
+}
+
+// 直接作为处理器使用时，不匹配前缀的请求返回 404
+impl Handler for Proxy {
+    fn handle(&self, req: &Request) -> Response {
+        if !self.matches(req) {
+            return Response::text(404, "Not Found\n");
+        }
+        self.forward(req)
+    }
+
+    // 有 body 的请求边读边转发，不在内存中缓存整个 body
+    fn streams_body(&self, req: &Request) -> bool {
+        self.matches(req) && (req.is_chunked() || req.content_length().is_ok_and(|len| len > 0))
+    }
+
+    fn handle_with_body(&self, req: &Request, body: &mut dyn Read) -> Response {
+        if !self.matches(req) {
+            return Response::text(404, "Not Found\n");
+        }
+        self.forward_with(req, Some(body))
+    }
+}
+
+// 转发失败的原因，只有上游的错误才计入健康检查
+enum Failure {
+    Client(io::Error),
+    Upstream(io::Error),
+}
+
+// 把客户端的 body 复制到上游，len 为 None 时按 chunked 编码发送
+fn send_body(
+    stream: &mut TcpStream,
+    body: &mut dyn Read,
+    len: Option<usize>,
+) -> Result<(), Failure> {
+    let mut buf = [0; 8192];
+    let mut sent = 0;
+    loop {
+        let n = match body.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Failure::Client(e)),
+        };
+        let result = match len {
+            Some(_) => stream.write_all(&buf[..n]),
+            None => write!(stream, "{:x}\r\n", n)
+                .and_then(|()| stream.write_all(&buf[..n]))
+                .and_then(|()| stream.write_all(b"\r\n")),
+        };
+        result.map_err(Failure::Upstream)?;
+        sent += n;
+    }
+    match len {
+        None => stream.write_all(b"0\r\n\r\n").map_err(Failure::Upstream),
+        // 客户端在 body 发送完之前关闭了连接
+        Some(len) if sent < len => Err(Failure::Client(io::ErrorKind::UnexpectedEof.into())),
+        Some(_) => Ok(()),
+    }
+}
+
+fn client_error_response(e: &io::Error) -> Response {
+    match e.kind() {
+        io::ErrorKind::FileTooLarge => Response::text(413, "Payload Too Large\n"),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            Response::text(408, "Request Timeout\n")
+        }
+        _ => Response::text(400, "Bad Request\n"),
+    }
+}
+
+fn error_response(e: &io::Error) -> Response {
+    match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            Response::text(504, "Gateway Timeout\n")
+        }
+        _ => Response::text(502, "Bad Gateway\n"),
+    }
+}
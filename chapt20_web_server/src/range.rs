@@ -0,0 +1,212 @@
+use std::{
+    io::{self, Cursor, Read},
+    time::SystemTime,
+};
+
+// 一次请求最多接受的区间数，防止用大量小区间放大响应
+const MAX_RANGES: usize = 16;
+
+// 闭区间 [start, end]，单位是字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        if self.is_empty() {
+            0
+        } else {
+            self.end - self.start + 1
+        }
+    }
+
+    /// 闭区间只有在 `end < start` 时才为空，`parse_range` 不会返回这样的区间。
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// `Content-Range` 的值，例如 `bytes 0-499/1234`。
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeError {
+    // 语法错误，按规范应当忽略 Range 返回完整内容
+    Invalid,
+    // 没有一个区间落在文件内，返回 416
+    Unsatisfiable,
+}
+
+/// 解析 `Range: bytes=...`，`len` 是资源的总长度。
+///
+/// 支持 `500-999`、`9500-` 和 `-500` 三种写法，
+/// 落在资源之外的区间会被丢弃，区间按请求顺序返回。
+pub fn parse_range(header: &str, len: u64) -> Result<Vec<ByteRange>, RangeError> {
+    let specs = header
+        .trim()
+        .strip_prefix("bytes=")
+        .ok_or(RangeError::Invalid)?;
+
+    let specs: Vec<&str> = specs
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    // bytes= 后面没有区间是语法错误，不是无法满足
+    if specs.is_empty() {
+        return Err(RangeError::Invalid);
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs {
+        let (start, end) = spec.split_once('-').ok_or(RangeError::Invalid)?;
+        let (start, end) = (start.trim(), end.trim());
+
+        let range = if start.is_empty() {
+            // -500 表示最后 500 个字节
+            let suffix: u64 = end.parse().map_err(|_| RangeError::Invalid)?;
+            if suffix == 0 || len == 0 {
+                continue;
+            }
+            ByteRange {
+                start: len.saturating_sub(suffix),
+                end: len - 1,
+            }
+        } else {
+            let start: u64 = start.parse().map_err(|_| RangeError::Invalid)?;
+            let end = if end.is_empty() {
+                u64::MAX
+            } else {
+                end.parse().map_err(|_| RangeError::Invalid)?
+            };
+            if end < start {
+                return Err(RangeError::Invalid);
+            }
+            if start >= len {
+                continue;
+            }
+            ByteRange {
+                start,
+                end: end.min(len - 1),
+            }
+        };
+        ranges.push(range);
+    }
+
+    if ranges.len() > MAX_RANGES {
+        return Err(RangeError::Invalid);
+    }
+    if ranges.is_empty() {
+        return Err(RangeError::Unsatisfiable);
+    }
+    Ok(ranges)
+}
+
+// multipart/byteranges 响应体
+// 每个区间前面是一段 part header，内容在写出时才从数据源读取
+pub struct MultipartRanges {
+    pub boundary: String,
+    parts: Vec<(Vec<u8>, ByteRange)>,
+    trailer: Vec<u8>,
+}
+
+impl MultipartRanges {
+    pub fn new(ranges: &[ByteRange], total: u64, content_type: &str) -> MultipartRanges {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let boundary = format!("{:032x}", nanos);
+
+        let parts = ranges
+            .iter()
+            .map(|range| {
+                let head = format!(
+                    "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                    boundary,
+                    content_type,
+                    range.content_range(total)
+                );
+                (head.into_bytes(), *range)
+            })
+            .collect();
+        let trailer = format!("\r\n--{}--\r\n", boundary).into_bytes();
+
+        MultipartRanges {
+            boundary,
+            parts,
+            trailer,
+        }
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/byteranges; boundary={}", self.boundary)
+    }
+
+    pub fn content_length(&self) -> u64 {
+        let parts: u64 = self
+            .parts
+            .iter()
+            .map(|(head, range)| head.len() as u64 + range.len())
+            .sum();
+        parts + self.trailer.len() as u64
+    }
+
+    /// 把各个区间串成一个 reader，`open` 负责打开数据源的某个区间。
+    pub fn into_reader<F>(self, mut open: F) -> io::Result<Box<dyn Read + Send>>
+    where
+        F: FnMut(ByteRange) -> io::Result<Box<dyn Read + Send>>,
+    {
+        let mut reader: Box<dyn Read + Send> = Box::new(io::empty());
+        for (head, range) in self.parts {
+            reader = Box::new(reader.chain(Cursor::new(head)).chain(open(range)?));
+        }
+        Ok(Box::new(reader.chain(Cursor::new(self.trailer))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_range_forms() {
+        let ranges = parse_range("bytes=0-499, 9500-, -500", 10000).unwrap();
+        assert_eq!(
+            vec![
+                ByteRange { start: 0, end: 499 },
+                ByteRange {
+                    start: 9500,
+                    end: 9999
+                },
+                ByteRange {
+                    start: 9500,
+                    end: 9999
+                },
+            ],
+            ranges
+        );
+    }
+
+    #[test]
+    fn range_length() {
+        assert_eq!(3, ByteRange { start: 2, end: 4 }.len());
+        assert!(!ByteRange { start: 4, end: 4 }.is_empty());
+        let empty = ByteRange { start: 5, end: 4 };
+        assert!(empty.is_empty());
+        assert_eq!(0, empty.len());
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        assert_eq!(Err(RangeError::Invalid), parse_range("items=0-1", 10));
+        assert_eq!(Err(RangeError::Invalid), parse_range("bytes=5-1", 10));
+        assert_eq!(Err(RangeError::Invalid), parse_range("bytes=", 10));
+        assert_eq!(Err(RangeError::Invalid), parse_range("bytes= , ", 10));
+        assert_eq!(Err(RangeError::Unsatisfiable), parse_range("bytes=10-", 10));
+    }
+}
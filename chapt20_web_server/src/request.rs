@@ -0,0 +1,139 @@
+use std::{
+    io::{self, BufRead, Read},
+    net::SocketAddr,
+};
+
+use crate::http::{invalid_data, read_chunked, read_head, ChunkedReader, Headers};
+
+// request
+// Method Request-URI HTTP-Version CRLF
+// headers CRLF
+// message-body
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub version: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+    pub peer_addr: Option<SocketAddr>,
+}
+
+impl Request {
+    pub fn new(method: &str, target: &str) -> Request {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (target.to_string(), None),
+        };
+
+        Request {
+            method: method.to_string(),
+            path,
+            query,
+            version: "HTTP/1.1".to_string(),
+            headers: Headers::new(),
+            body: Vec::new(),
+            peer_addr: None,
+        }
+    }
+
+    /// 从流中读取请求行和 header。
+    ///
+    /// 连接在请求开始前被关闭时返回 `Ok(None)`。
+    ///
+    /// # Errors
+    ///
+    /// 请求格式错误时返回 `InvalidData` 错误。
+    pub fn read_from(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+        let (request_line, headers) = match read_head(reader)? {
+            Some(head) => head,
+            None => return Ok(None),
+        };
+
+        let mut parts = request_line.split(' ');
+        let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version))
+                if parts.next().is_none() && version.starts_with("HTTP/") =>
+            {
+                (method, target, version)
+            }
+            _ => return Err(invalid_data("malformed request line")),
+        };
+
+        let mut request = Request::new(method, target);
+        request.version = version.to_string();
+        request.headers = headers;
+        Ok(Some(request))
+    }
+
+    /// 根据 `Content-Length` 或 `Transfer-Encoding: chunked` 读取 body。
+    ///
+    /// # Errors
+    ///
+    /// body 超过 `limit` 字节时返回 `FileTooLarge`，格式错误时返回 `InvalidData`。
+    pub fn read_body(&mut self, reader: &mut impl BufRead, limit: usize) -> io::Result<()> {
+        if self.is_chunked() {
+            self.body = read_chunked(reader, limit)?;
+            return Ok(());
+        }
+
+        let len = self.content_length()?;
+        if len > limit {
+            return Err(io::Error::from(io::ErrorKind::FileTooLarge));
+        }
+        self.body = vec![0; len];
+        reader.read_exact(&mut self.body)
+    }
+
+    /// 不把 body 读入内存，返回按 `Content-Length` 或 chunked 编码读取 body 的 reader。
+    ///
+    /// # Errors
+    ///
+    /// `Content-Length` 超过 `limit` 时返回 `FileTooLarge`，格式错误时返回 `InvalidData`；
+    /// chunked 编码的 body 在读取时检查长度。
+    pub fn body_reader<'a>(
+        &self,
+        reader: &'a mut impl BufRead,
+        limit: usize,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        if self.is_chunked() {
+            return Ok(Box::new(ChunkedReader::new(reader, limit)));
+        }
+        let len = self.content_length()?;
+        if len > limit {
+            return Err(io::Error::from(io::ErrorKind::FileTooLarge));
+        }
+        Ok(Box::new(reader.take(len as u64)))
+    }
+
+    /// 请求声明的 body 长度，没有 `Content-Length` 时为 0。
+    pub fn content_length(&self) -> io::Result<usize> {
+        match self.header("Content-Length") {
+            Some(len) => len
+                .trim()
+                .parse()
+                .map_err(|_| invalid_data("bad Content-Length")),
+            None => Ok(0),
+        }
+    }
+
+    pub fn is_chunked(&self) -> bool {
+        self.header("Transfer-Encoding")
+            .is_some_and(|te| te.to_ascii_lowercase().contains("chunked"))
+    }
+
+    /// `Content-Type` 去掉参数后的部分，例如 `application/json`。
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("Content-Type")
+            .map(|ct| ct.split(';').next().unwrap_or("").trim())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    pub fn is_head(&self) -> bool {
+        self.method == "HEAD"
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Read, Write},
+};
+
+use crate::{
+    http::{invalid_data, read_head, reason_phrase, Headers},
+    server::Upgraded,
+};
+
+// response
+// HTTP-Version Status-Code Reason-Phrase CRLF
+// headers CRLF
+// message-body
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Headers,
+    pub body: Body,
+    pub on_upgrade: Option<OnUpgrade>,
+}
+
+// 101 响应写出后接管连接的回调
+pub struct OnUpgrade(pub Box<dyn FnOnce(Upgraded) + Send>);
+
+impl fmt::Debug for OnUpgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OnUpgrade")
+    }
+}
+
+pub enum Body {
+    Empty,
+    Bytes(Vec<u8>),
+    // 边读边写的 body，例如磁盘上的文件
+    // len 为 None 时 body 一直读到 EOF，写完后关闭连接
+    Stream {
+        reader: Box<dyn Read + Send>,
+        len: Option<u64>,
+    },
+}
+
+impl Body {
+    pub fn stream(reader: impl Read + Send + 'static, len: Option<u64>) -> Body {
+        Body::Stream {
+            reader: Box::new(reader),
+            len,
+        }
+    }
+
+    /// body 的长度，长度未知时返回 `None`。
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Body::Empty => Some(0),
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Stream { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// 把 body 全部读入内存。
+    pub fn into_bytes(self) -> io::Result<Vec<u8>> {
+        match self {
+            Body::Empty => Ok(Vec::new()),
+            Body::Bytes(bytes) => Ok(bytes),
+            Body::Stream { mut reader, .. } => {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Empty => write!(f, "Empty"),
+            Body::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Body::Stream { len, .. } => write!(f, "Stream({:?})", len),
+        }
+    }
+}
+
+impl Response {
+    pub fn new(status: u16) -> Response {
+        Response {
+            status,
+            headers: Headers::new(),
+            body: Body::Empty,
+            on_upgrade: None,
+        }
+    }
+
+    pub fn html(status: u16, contents: impl Into<Vec<u8>>) -> Response {
+        Response::new(status)
+            .with_header("Content-Type", "text/html; charset=utf-8")
+            .with_body(contents)
+    }
+
+    pub fn text(status: u16, contents: impl Into<Vec<u8>>) -> Response {
+        Response::new(status)
+            .with_header("Content-Type", "text/plain; charset=utf-8")
+            .with_body(contents)
+    }
+
+    pub fn json(status: u16, value: &impl serde::Serialize) -> Response {
+        match serde_json::to_vec(value) {
+            Ok(body) => Response::new(status)
+                .with_header("Content-Type", "application/json")
+                .with_body(body),
+            Err(e) => Response::text(500, format!("failed to serialize JSON: {}\n", e)),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Response {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Response {
+        self.body = Body::Bytes(body.into());
+        self
+    }
+
+    /// 使用长度为 `len` 的流作为 body。
+    pub fn with_stream(mut self, reader: impl Read + Send + 'static, len: u64) -> Response {
+        self.body = Body::stream(reader, Some(len));
+        self
+    }
+
+    /// 读取状态行和 header，body 留给调用者按 header 读取。
+    ///
+    /// # Errors
+    ///
+    /// 连接提前关闭时返回 `UnexpectedEof`，格式错误时返回 `InvalidData`。
+    pub fn read_head(reader: &mut impl BufRead) -> io::Result<Response> {
+        let (status_line, headers) =
+            read_head(reader)?.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+        // HTTP/1.1 200 OK
+        let mut parts = status_line.splitn(3, ' ');
+        let status = match (parts.next(), parts.next()) {
+            (Some(version), Some(status)) if version.starts_with("HTTP/") => status
+                .parse()
+                .map_err(|_| invalid_data("malformed status line"))?,
+            _ => return Err(invalid_data("malformed status line")),
+        };
+
+        let mut response = Response::new(status);
+        response.headers = headers;
+        Ok(response)
+    }
+
+    /// 写出 101 响应后在当前线程中调用 `f`，由 `f` 接管连接。
+    pub fn with_upgrade(mut self, f: impl FnOnce(Upgraded) + Send + 'static) -> Response {
+        self.on_upgrade = Some(OnUpgrade(Box::new(f)));
+        self
+    }
+
+    /// 写出状态行、header 和 body。
+    ///
+    /// `Content-Length` 根据 body 自动补上；`head_only` 为 true 时
+    /// （HEAD 请求）只写出 header。
+    pub fn write_to(mut self, w: &mut impl Write, head_only: bool) -> io::Result<()> {
+        // 1xx、204 和 304 响应没有 body
+        let has_body = !matches!(self.status, 100..=199 | 204 | 304);
+        if has_body && !self.headers.contains("Content-Length") {
+            if let Some(len) = self.body.len() {
+                self.headers.insert("Content-Length", len.to_string());
+            }
+        }
+
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status,
+            reason_phrase(self.status)
+        );
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        w.write_all(head.as_bytes())?;
+
+        if !head_only {
+            match self.body {
+                Body::Empty => {}
+                Body::Bytes(bytes) => w.write_all(&bytes)?,
+                Body::Stream { mut reader, .. } => {
+                    io::copy(&mut reader, w)?;
+                }
+            }
+        }
+
+        w.flush()
+    }
+}
@@ -0,0 +1,93 @@
+use std::{io::Read, time::Duration};
+
+use crate::{request::Request, response::Response, server::Handler};
+
+// 给每个响应加上安全相关的 header
+// 处理函数已经设置的 header 不会被覆盖
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    headers: Vec<(String, String)>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> SecurityHeaders {
+        SecurityHeaders::new()
+    }
+}
+
+impl SecurityHeaders {
+    /// 默认的 header：只允许同源资源的 CSP、`nosniff` 和禁止被嵌入 frame。
+    ///
+    /// HSTS 只对 HTTPS 有意义，需要用 [`SecurityHeaders::hsts`] 开启。
+    pub fn new() -> SecurityHeaders {
+        SecurityHeaders {
+            headers: Vec::new(),
+        }
+        .header("Content-Security-Policy", "default-src 'self'")
+        .header("X-Content-Type-Options", "nosniff")
+        .header("X-Frame-Options", "DENY")
+        .header("Referrer-Policy", "no-referrer")
+    }
+
+    /// 设置或替换一个 header。
+    pub fn header(mut self, name: &str, value: &str) -> SecurityHeaders {
+        self = self.without(name);
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// 不再添加 `name`。
+    pub fn without(mut self, name: &str) -> SecurityHeaders {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self
+    }
+
+    pub fn content_security_policy(self, policy: &str) -> SecurityHeaders {
+        self.header("Content-Security-Policy", policy)
+    }
+
+    /// 开启 `Strict-Transport-Security`，浏览器在 `max_age` 内只用 HTTPS 访问。
+    pub fn hsts(self, max_age: Duration, include_subdomains: bool) -> SecurityHeaders {
+        let mut value = format!("max-age={}", max_age.as_secs());
+        if include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        self.header("Strict-Transport-Security", &value)
+    }
+
+    pub fn apply(&self, mut response: Response) -> Response {
+        for (name, value) in &self.headers {
+            if !response.headers.contains(name) {
+                response.headers.insert(name, value.clone());
+            }
+        }
+        response
+    }
+
+    pub fn wrap<H: Handler>(self, handler: H) -> SecurityHeadersHandler<H> {
+        SecurityHeadersHandler {
+            headers: self,
+            handler,
+        }
+    }
+}
+
+/// [`SecurityHeaders::wrap`] 返回的处理器，请求 body 的读取方式由内层处理器决定。
+pub struct SecurityHeadersHandler<H> {
+    headers: SecurityHeaders,
+    handler: H,
+}
+
+impl<H: Handler> Handler for SecurityHeadersHandler<H> {
+    fn handle(&self, req: &Request) -> Response {
+        self.headers.apply(self.handler.handle(req))
+    }
+
+    fn streams_body(&self, req: &Request) -> bool {
+        self.handler.streams_body(req)
+    }
+
+    fn handle_with_body(&self, req: &Request, body: &mut dyn Read) -> Response {
+        self.headers.apply(self.handler.handle_with_body(req, body))
+    }
+}
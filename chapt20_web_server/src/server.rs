@@ -0,0 +1,413 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    limit::{Limited, Limiter},
+    request::Request,
+    response::Response,
+    ThreadPool,
+};
+
+// 处理请求并生成响应
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, req: &Request) -> Response;
+
+    /// 返回 true 时服务器不把请求 body 读入内存，而是调用 `handle_with_body`，
+    /// 例如边读边转发的反向代理。这样的连接处理完后会被关闭。
+    fn streams_body(&self, _req: &Request) -> bool {
+        false
+    }
+
+    /// `streams_body` 返回 true 时调用，`req.body` 为空，body 从 `body` 中读取。
+    fn handle_with_body(&self, req: &Request, _body: &mut dyn Read) -> Response {
+        self.handle(req)
+    }
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    fn handle(&self, req: &Request) -> Response {
+        self(req)
+    }
+}
+
+// 请求 body 的默认大小上限
+const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+// 读取请求头的默认时间上限
+const DEFAULT_HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 等待回复 429 的连接的队列长度，队列满时直接关闭连接
+const REJECT_QUEUE: usize = 64;
+
+// 监听 TCP 连接，并把每个连接交给线程池处理
+pub struct Server {
+    listener: TcpListener,
+    pool: ThreadPool,
+    handler: Arc<dyn Handler>,
+    max_body_size: usize,
+    header_timeout: Duration,
+    keep_alive: Option<Duration>,
+    limiter: Option<Arc<Limiter>>,
+}
+
+impl Server {
+    /// 绑定地址并创建 `threads` 个线程的线程池。
+    ///
+    /// # Panics
+    ///
+    /// `threads` 为 0 时会 panic。
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        threads: usize,
+        handler: impl Handler,
+    ) -> io::Result<Server> {
+        let listener = TcpListener::bind(addr)?;
+
+        Ok(Server {
+            listener,
+            pool: ThreadPool::new(threads),
+            handler: Arc::new(handler),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            header_timeout: DEFAULT_HEADER_TIMEOUT,
+            keep_alive: None,
+            limiter: None,
+        })
+    }
+
+    /// 设置请求 body 的大小上限，超过时返回 413。
+    pub fn max_body_size(mut self, bytes: usize) -> Server {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// 读取请求头的总时间上限，默认 10 秒，超时时返回 408 并关闭连接。
+    ///
+    /// 读取 body 时作为每次读取的超时，空闲或很慢的客户端不会一直占用工作线程。
+    pub fn header_timeout(mut self, timeout: Duration) -> Server {
+        self.header_timeout = timeout;
+        self
+    }
+
+    /// 开启 keep-alive，空闲 `timeout` 后关闭连接。
+    ///
+    /// 保持的连接会一直占用线程池中的一个线程，默认关闭。
+    pub fn keep_alive(mut self, timeout: Duration) -> Server {
+        self.keep_alive = Some(timeout);
+        self
+    }
+
+    /// 按 `limiter` 限制连接数和请求速率，超过限制时返回 429。
+    ///
+    /// 保留一份 `limiter` 可以通过 `Limiter::stats` 观察限流情况。
+    pub fn limiter(mut self, limiter: Arc<Limiter>) -> Server {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) {
+        // 被拒绝的连接由单独的线程回复，不占用线程池
+        let rejecter = self.limiter.as_ref().map(|_| {
+            let (sender, receiver) = mpsc::sync_channel(REJECT_QUEUE);
+            thread::spawn(move || {
+                for (stream, limited) in receiver {
+                    reject(stream, limited);
+                }
+            });
+            sender
+        });
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+
+            let guard = match (&self.limiter, &rejecter) {
+                (Some(limiter), Some(rejecter)) => {
+                    let ip = match stream.peer_addr() {
+                        Ok(addr) => addr.ip(),
+                        Err(e) => {
+                            eprintln!("Failed to get peer address: {}", e);
+                            continue;
+                        }
+                    };
+                    match limiter.acquire(ip) {
+                        Ok(guard) => Some(guard),
+                        Err(limited) => {
+                            // 队列满时直接关闭连接
+                            let _ = rejecter.try_send((stream, limited));
+                            continue;
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            let handler = Arc::clone(&self.handler);
+            let limiter = self.limiter.clone();
+            let max_body_size = self.max_body_size;
+            let header_timeout = self.header_timeout;
+            let keep_alive = self.keep_alive;
+            self.pool.execute(move || {
+                // 升级后接管连接的回调在 handle 之外运行，panic 时也不能杀死工作线程
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    handle_connection(
+                        stream,
+                        handler.as_ref(),
+                        max_body_size,
+                        header_timeout,
+                        keep_alive,
+                        limiter.as_deref(),
+                    )
+                }));
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => eprintln!("Connection error: {}", e),
+                    Err(_) => eprintln!("Connection handler panicked"),
+                }
+                // 连接处理完后才归还连接计数
+                drop(guard);
+            });
+        }
+    }
+}
+
+// 不等待请求直接回复 429，避免慢客户端拖住这个线程
+fn reject(mut stream: TcpStream, limited: Limited) {
+    // 先读掉已经到达的请求数据，关闭时接收缓冲区中有未读数据会导致 RST，客户端可能收不到响应
+    if stream.set_nonblocking(true).is_ok() {
+        let mut buf = [0; 4096];
+        for _ in 0..16 {
+            match stream.read(&mut buf) {
+                Ok(n) if n > 0 => continue,
+                _ => break,
+            }
+        }
+    }
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_write_timeout(Some(Duration::from_millis(500)));
+    let _ = limited.into_response().write_to(&mut stream, false);
+}
+
+// 处理请求方法
+// 请求头需要在 header_timeout 内读完，keep_alive 为 Some 时一个连接上可以依次处理多个请求
+pub fn handle_connection(
+    mut stream: TcpStream,
+    handler: &dyn Handler,
+    max_body_size: usize,
+    header_timeout: Duration,
+    keep_alive: Option<Duration>,
+    limiter: Option<&Limiter>,
+) -> io::Result<()> {
+    // 响应头和 body 分两次写出，关闭 Nagle 算法以免等待延迟确认
+    stream.set_nodelay(true)?;
+    let mut buf_reader = BufReader::new(TimedStream {
+        stream: stream.try_clone()?,
+        deadline: None,
+    });
+
+    let mut first = true;
+    loop {
+        // 等待下一个请求的第一个字节，第一个请求也受 header_timeout 限制
+        let idle = match keep_alive {
+            Some(keep_alive) if !first => keep_alive,
+            _ => header_timeout,
+        };
+        first = false;
+        buf_reader.get_mut().deadline = Some(Instant::now() + idle);
+        match buf_reader.fill_buf() {
+            Ok([]) => return Ok(()),
+            Ok(_) => {}
+            // 空闲超时，还没有收到请求，直接关闭
+            Err(e) if is_timeout(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        buf_reader.get_mut().deadline = Some(Instant::now() + header_timeout);
+        let request = Request::read_from(&mut buf_reader);
+        // 读取 body 时每次读取最多等待 header_timeout
+        buf_reader.get_mut().deadline = None;
+        stream.set_read_timeout(Some(header_timeout))?;
+        let mut request = match request {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let response =
+                    Response::text(400, "Bad Request\n").with_header("Connection", "close");
+                return response.write_to(&mut stream, false);
+            }
+            Err(e) if is_timeout(&e) => {
+                let response =
+                    Response::text(408, "Request Timeout\n").with_header("Connection", "close");
+                return response.write_to(&mut stream, false);
+            }
+            Err(e) => return Err(e),
+        };
+        request.peer_addr = stream.peer_addr().ok();
+
+        // 先检查速率，被限制的请求不需要读取 body
+        if let (Some(limiter), Some(peer)) = (limiter, request.peer_addr) {
+            if let Err(limited) = limiter.check_rate(peer.ip()) {
+                return limited.into_response().write_to(&mut stream, false);
+            }
+        }
+
+        // 客户端等待 100 Continue 后才会发送 body
+        let expects_continue = request
+            .header("Expect")
+            .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"));
+        if expects_continue && request.content_length().unwrap_or(0) <= max_body_size {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        }
+
+        // 流式读取 body 的处理器在读取时才检查 body，这里只提前检查 Content-Length
+        let streaming = handler.streams_body(&request);
+        // 流式的 body 借用了 buf_reader，处理完后才能把连接交给升级的处理器
+        let mut response = {
+            let body = if streaming {
+                request
+                    .body_reader(&mut buf_reader, max_body_size)
+                    .map(Some)
+            } else {
+                request
+                    .read_body(&mut buf_reader, max_body_size)
+                    .map(|()| None)
+            };
+            let mut body = match body {
+                Ok(body) => body,
+                Err(e) if e.kind() == io::ErrorKind::FileTooLarge => {
+                    let response = Response::text(413, "Payload Too Large\n")
+                        .with_header("Connection", "close");
+                    return response.write_to(&mut stream, false);
+                }
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    let response =
+                        Response::text(400, "Bad Request\n").with_header("Connection", "close");
+                    return response.write_to(&mut stream, false);
+                }
+                Err(e) => return Err(e),
+            };
+
+            // 处理函数 panic 时返回 500，工作线程继续处理其他连接
+            panic::catch_unwind(AssertUnwindSafe(|| match &mut body {
+                Some(body) => handler.handle_with_body(&request, body),
+                None => handler.handle(&request),
+            }))
+            .unwrap_or_else(|_| {
+                eprintln!("Handler panicked on {} {}", request.method, request.path);
+                Response::text(500, "Internal Server Error\n").with_header("Connection", "close")
+            })
+        };
+        // 处理器不一定读完了流式的 body，连接中剩下的数据无法跳过
+        // 长度未知的 body 要靠关闭连接来结束
+        let reuse = keep_alive.is_some()
+            && !streaming
+            && wants_keep_alive(&request)
+            && response.on_upgrade.is_none()
+            && response.body.len().is_some()
+            && !response
+                .headers
+                .get("Connection")
+                .is_some_and(|c| c.eq_ignore_ascii_case("close"));
+        if !response.headers.contains("Connection") {
+            let connection = if reuse { "keep-alive" } else { "close" };
+            response.headers.insert("Connection", connection);
+        }
+
+        // 协议升级（如 WebSocket）：写完 101 响应后由处理器接管连接
+        let on_upgrade = response.on_upgrade.take();
+        response.write_to(&mut stream, request.is_head())?;
+        if let Some(on_upgrade) = on_upgrade {
+            stream.set_read_timeout(None)?;
+            (on_upgrade.0)(Upgraded {
+                reader: buf_reader,
+                stream,
+            });
+            return Ok(());
+        }
+        if !reuse {
+            return Ok(());
+        }
+    }
+}
+
+// HTTP/1.1 默认保持连接，HTTP/1.0 需要显式请求
+fn wants_keep_alive(req: &Request) -> bool {
+    match req.header("Connection") {
+        Some(c) if c.eq_ignore_ascii_case("close") => false,
+        Some(c) if c.eq_ignore_ascii_case("keep-alive") => true,
+        _ => req.version == "HTTP/1.1",
+    }
+}
+
+pub(crate) fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+// 在截止时间前读取，每次读取前把剩余的时间设为读取超时
+// deadline 为 None 时使用 TcpStream 上设置的超时
+struct TimedStream {
+    stream: TcpStream,
+    deadline: Option<Instant>,
+}
+
+impl Read for TimedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| !remaining.is_zero())
+                .ok_or(io::ErrorKind::TimedOut)?;
+            self.stream.set_read_timeout(Some(remaining))?;
+        }
+        self.stream.read(buf)
+    }
+}
+
+// 升级后的连接
+// 读取时先读出 BufReader 中已经缓冲的数据，写入直接写到 TcpStream
+pub struct Upgraded {
+    reader: BufReader<TimedStream>,
+    stream: TcpStream,
+}
+
+impl Upgraded {
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl Read for Upgraded {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Write for Upgraded {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
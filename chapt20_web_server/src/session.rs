@@ -0,0 +1,328 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    cookie::{Cookie, SameSite},
+    request::Request,
+    response::Response,
+    server::Handler,
+};
+
+pub type SessionData = BTreeMap<String, String>;
+
+// 保存会话数据的地方
+pub trait SessionStore: Send + Sync + 'static {
+    fn load(&self, id: &str) -> Option<SessionData>;
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration);
+    fn remove(&self, id: &str);
+}
+
+// 内存中的会话，过期的会话在保存新会话时顺便清理
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, (Instant, SessionData)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Option<SessionData> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(id) {
+            Some((expires, data)) if *expires > Instant::now() => Some(data.clone()),
+            Some(_) => {
+                sessions.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, (expires, _)| *expires > now);
+        sessions.insert(id.to_string(), (now + ttl, data.clone()));
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+}
+
+// 每个会话保存为目录下的一个 JSON 文件，服务器重启后会话仍然有效
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    expires: u64,
+    data: SessionData,
+}
+
+impl FileStore {
+    /// 使用 `dir` 保存会话，目录不存在时会创建。
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<FileStore> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileStore { dir })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> Option<SessionData> {
+        let contents = fs::read(self.path(id)).ok()?;
+        let stored: StoredSession = serde_json::from_slice(&contents).ok()?;
+        if stored.expires <= unix_now() {
+            self.remove(id);
+            return None;
+        }
+        Some(stored.data)
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) {
+        let stored = StoredSession {
+            expires: unix_now() + ttl.as_secs(),
+            data: data.clone(),
+        };
+        let result = serde_json::to_vec(&stored)
+            .map_err(io::Error::from)
+            .and_then(|contents| fs::write(self.path(id), contents));
+        if let Err(e) = result {
+            eprintln!("Failed to save session {}: {}", id, e);
+        }
+    }
+
+    fn remove(&self, id: &str) {
+        let _ = fs::remove_file(self.path(id));
+    }
+}
+
+// 一次请求中的会话
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    id: Option<String>,
+    data: SessionData,
+    renew: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.data.get(key).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, key: &str, value: impl Into<String>) {
+        self.data.insert(key.to_string(), value.into());
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.data.remove(key)
+    }
+
+    /// 更换会话 ID，登录成功后调用以防止会话固定攻击。
+    pub fn renew(&mut self) {
+        self.renew = true;
+    }
+
+    /// 删除会话，例如退出登录。
+    pub fn destroy(&mut self) {
+        self.data.clear();
+        self.destroyed = true;
+    }
+
+    pub fn is_new(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 会话管理：从签名 cookie 中取出会话 ID，在 store 中读写会话数据
+pub struct Sessions {
+    store: Box<dyn SessionStore>,
+    secret: Vec<u8>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl Sessions {
+    /// `secret` 用来给 cookie 中的会话 ID 签名。
+    ///
+    /// # Panics
+    ///
+    /// `secret` 少于 32 字节时会 panic。
+    pub fn new(store: impl SessionStore, secret: &[u8]) -> Sessions {
+        assert!(
+            secret.len() >= 32,
+            "session secret must be at least 32 bytes"
+        );
+
+        Sessions {
+            store: Box::new(store),
+            secret: secret.to_vec(),
+            cookie_name: "session".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: false,
+        }
+    }
+
+    pub fn cookie_name(mut self, name: &str) -> Sessions {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    /// 会话的有效期，每次保存都会重新计时。
+    pub fn ttl(mut self, ttl: Duration) -> Sessions {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 只通过 HTTPS 发送会话 cookie。
+    pub fn secure(mut self, secure: bool) -> Sessions {
+        self.secure = secure;
+        self
+    }
+
+    /// 读取请求对应的会话，cookie 缺失、签名不对或会话已过期时返回新会话。
+    pub fn load(&self, req: &Request) -> Session {
+        let id = req
+            .cookie(&self.cookie_name)
+            .and_then(|value| self.verify(&value));
+
+        match id.and_then(|id| self.store.load(&id).map(|data| (id, data))) {
+            Some((id, data)) => Session {
+                id: Some(id),
+                data,
+                ..Session::default()
+            },
+            None => Session::default(),
+        }
+    }
+
+    /// 保存会话，需要时在响应中设置或删除 cookie。
+    pub fn save(&self, session: Session, mut response: Response) -> Response {
+        if session.destroyed {
+            if let Some(id) = &session.id {
+                self.store.remove(id);
+                response = response.with_cookie(&Cookie::removal(&self.cookie_name));
+            }
+            return response;
+        }
+
+        // 没有数据的新会话不需要保存
+        if session.is_new() && session.data.is_empty() {
+            return response;
+        }
+
+        let id = match session.id {
+            Some(id) if !session.renew => id,
+            old => {
+                if let Some(old) = old {
+                    self.store.remove(&old);
+                }
+                new_session_id()
+            }
+        };
+        // 每次保存都刷新过期时间
+        self.store.save(&id, &session.data, self.ttl);
+
+        let cookie = Cookie::new(&self.cookie_name, &self.sign(&id))
+            .path("/")
+            .max_age(self.ttl)
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax);
+        response.with_cookie(&cookie)
+    }
+
+    /// 把需要会话的处理函数包装成 `Handler`。
+    pub fn wrap<F>(self, f: F) -> SessionsHandler<F>
+    where
+        F: Fn(&Request, &mut Session) -> Response + Send + Sync + 'static,
+    {
+        SessionsHandler { sessions: self, f }
+    }
+
+    // cookie 的值是 `id.签名`
+    fn sign(&self, id: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).unwrap();
+        mac.update(id.as_bytes());
+        format!("{}.{}", id, to_hex(&mac.finalize().into_bytes()))
+    }
+
+    fn verify(&self, value: &str) -> Option<String> {
+        let (id, signature) = value.rsplit_once('.')?;
+        let signature = from_hex(signature)?;
+
+        let mut mac = HmacSha256::new_from_slice(&self.secret).unwrap();
+        mac.update(id.as_bytes());
+        // verify_slice 是常数时间比较
+        mac.verify_slice(&signature).ok()?;
+        Some(id.to_string())
+    }
+}
+
+/// [`Sessions::wrap`] 返回的处理器，处理函数收到的请求 body 已经读完。
+pub struct SessionsHandler<F> {
+    sessions: Sessions,
+    f: F,
+}
+
+impl<F> Handler for SessionsHandler<F>
+where
+    F: Fn(&Request, &mut Session) -> Response + Send + Sync + 'static,
+{
+    fn handle(&self, req: &Request) -> Response {
+        let mut session = self.sessions.load(req);
+        let response = (self.f)(req, &mut session);
+        self.sessions.save(session, response)
+    }
+}
+
+// 128 位随机数作为会话 ID
+fn new_session_id() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    to_hex(&bytes)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
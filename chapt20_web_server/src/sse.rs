@@ -0,0 +1,262 @@
+use std::{
+    fmt,
+    io::{self, Read},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    request::Request,
+    response::{Body, Response},
+    Shutdown,
+};
+
+// 检查线程池是否关闭的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// 发送方最多缓存的事件数，写不出去时 send 会阻塞
+const CHANNEL_CAPACITY: usize = 64;
+
+// 一条 Server-Sent Event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    retry: Option<Duration>,
+    data: String,
+}
+
+impl Event {
+    /// 多行的 `data` 会拆成多个 `data:` 字段，客户端收到后再用换行拼起来。
+    pub fn new(data: impl Into<String>) -> Event {
+        Event {
+            data: data.into(),
+            ..Event::default()
+        }
+    }
+
+    /// 客户端重连时会把最后收到的 id 放在 `Last-Event-ID` 中。
+    pub fn id(mut self, id: impl Into<String>) -> Event {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// 事件类型，客户端用 `addEventListener(type, ...)` 接收，默认是 `message`。
+    pub fn event(mut self, event: impl Into<String>) -> Event {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// 断开后客户端等待多久重连。
+    pub fn retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+// id 和 event 中的换行会破坏帧结构，直接去掉
+fn single_line(value: &str) -> String {
+    value.chars().filter(|c| *c != '\r' && *c != '\n').collect()
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {}", single_line(id))?;
+        }
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {}", single_line(event))?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        for line in self.data.split('\n') {
+            writeln!(f, "data: {}", line.strip_suffix('\r').unwrap_or(line))?;
+        }
+        // 空行表示事件结束
+        writeln!(f)
+    }
+}
+
+// 客户端已经断开或服务器正在关闭
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event stream closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+// 向 SSE 连接发送事件，可以转移到其他线程中使用
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: SyncSender<Vec<u8>>,
+}
+
+impl EventSender {
+    /// # Errors
+    ///
+    /// 连接已经关闭时返回 `Closed`，生产事件的线程应该就此结束。
+    pub fn send(&self, event: &Event) -> Result<(), Closed> {
+        self.sender.send(event.to_bytes()).map_err(|_| Closed)
+    }
+}
+
+// text/event-stream 响应的配置
+#[derive(Debug, Clone)]
+pub struct EventStream {
+    heartbeat: Option<Duration>,
+    retry: Option<Duration>,
+}
+
+impl Default for EventStream {
+    fn default() -> EventStream {
+        EventStream {
+            heartbeat: Some(Duration::from_secs(15)),
+            retry: None,
+        }
+    }
+}
+
+impl EventStream {
+    pub fn new() -> EventStream {
+        EventStream::default()
+    }
+
+    /// 超过 `interval` 没有事件时发送注释行，防止代理因为空闲断开连接，
+    /// 也能及时发现客户端已经断开。`None` 表示不发送心跳。
+    pub fn heartbeat(mut self, interval: Option<Duration>) -> EventStream {
+        self.heartbeat = interval;
+        self
+    }
+
+    /// 在连接开始时告诉客户端断开后的重连间隔。
+    pub fn retry(mut self, retry: Duration) -> EventStream {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// 返回发送端和响应，响应在发送端全部丢弃或线程池关闭时结束。
+    ///
+    /// 响应必须在线程池的线程中写出，才能在 `ThreadPool` drop 时及时结束。
+    pub fn open(self) -> (EventSender, Response) {
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+
+        let mut pending = Vec::new();
+        if let Some(retry) = self.retry {
+            pending.extend_from_slice(format!("retry: {}\n\n", retry.as_millis()).as_bytes());
+        }
+        let reader = EventReader {
+            receiver,
+            pending,
+            heartbeat: self.heartbeat,
+            last_write: Instant::now(),
+            shutdown: None,
+        };
+
+        let mut response = Response::new(200)
+            .with_header("Content-Type", "text/event-stream")
+            .with_header("Cache-Control", "no-cache")
+            // 让 nginx 之类的反向代理不要缓冲
+            .with_header("X-Accel-Buffering", "no")
+            .with_header("Connection", "close");
+        response.body = Body::stream(reader, None);
+        (EventSender { sender }, response)
+    }
+}
+
+/// 使用默认配置打开事件流。
+pub fn channel() -> (EventSender, Response) {
+    EventStream::new().open()
+}
+
+/// 客户端重连时带上的最后一个事件 id。
+pub fn last_event_id(req: &Request) -> Option<&str> {
+    req.header("Last-Event-ID")
+}
+
+// 把信道中的事件作为响应 body 读出
+struct EventReader {
+    receiver: Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+    heartbeat: Option<Duration>,
+    last_write: Instant,
+    // 第一次读取时才获取，此时已经在写出响应的线程中
+    shutdown: Option<Shutdown>,
+}
+
+impl EventReader {
+    fn fill(&mut self) -> bool {
+        let shutdown = self.shutdown.get_or_insert_with(Shutdown::current).clone();
+        loop {
+            if shutdown.is_triggered() {
+                return false;
+            }
+            let wait = match self.heartbeat {
+                Some(interval) => interval
+                    .saturating_sub(self.last_write.elapsed())
+                    .min(POLL_INTERVAL),
+                None => POLL_INTERVAL,
+            };
+            match self.receiver.recv_timeout(wait) {
+                Ok(event) => {
+                    self.pending = event;
+                    return true;
+                }
+                Err(RecvTimeoutError::Disconnected) => return false,
+                Err(RecvTimeoutError::Timeout) => {
+                    if self
+                        .heartbeat
+                        .is_some_and(|interval| self.last_write.elapsed() >= interval)
+                    {
+                        self.pending = b": heartbeat\n\n".to_vec();
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Read for EventReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() && !self.fill() {
+            // 返回 EOF，服务器写完响应后关闭连接
+            return Ok(0);
+        }
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        self.last_write = Instant::now();
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_event_fields() {
+        let event = Event::new("line one\nline two")
+            .id("7")
+            .event("update")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            "id: 7\nevent: update\nretry: 3000\ndata: line one\ndata: line two\n\n",
+            event.to_string()
+        );
+        assert_eq!("data: \n\n", Event::new("").to_string());
+        assert_eq!(
+            "id: ab\ndata: x\n\n",
+            Event::new("x").id("a\nb").to_string()
+        );
+    }
+}
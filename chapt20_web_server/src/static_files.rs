@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{
+    http::{percent_decode, HttpDate},
+    range::{parse_range, MultipartRanges, RangeError},
+    request::Request,
+    response::Response,
+    server::Handler,
+};
+
+// 静态文件处理器
+// 为文件生成 ETag / Last-Modified，响应条件请求和 Range 请求
+pub struct StaticFiles {
+    root: PathBuf,
+    index: String,
+    not_found_page: Option<String>,
+    cache_control: Option<String>,
+    cache: Option<Mutex<FileCache>>,
+}
+
+impl StaticFiles {
+    pub fn new(root: impl Into<PathBuf>) -> StaticFiles {
+        StaticFiles {
+            root: root.into(),
+            index: "index.html".to_string(),
+            not_found_page: None,
+            cache_control: None,
+            cache: None,
+        }
+    }
+
+    /// 请求目录时返回的文件名，默认是 `index.html`。
+    pub fn index(mut self, name: &str) -> StaticFiles {
+        self.index = name.to_string();
+        self
+    }
+
+    /// 文件不存在时返回的页面（相对于根目录）。
+    pub fn not_found_page(mut self, name: &str) -> StaticFiles {
+        self.not_found_page = Some(name.to_string());
+        self
+    }
+
+    /// 每个文件响应都带上的 `Cache-Control`，例如 `public, max-age=3600`。
+    pub fn cache_control(mut self, value: &str) -> StaticFiles {
+        self.cache_control = Some(value.to_string());
+        self
+    }
+
+    /// 启用最多缓存 `max_bytes` 字节文件内容的 LRU 缓存。
+    ///
+    /// 每次命中都会检查文件的修改时间和大小，变化后重新读取。
+    pub fn with_cache(mut self, max_bytes: usize) -> StaticFiles {
+        self.cache = Some(Mutex::new(FileCache::new(max_bytes)));
+        self
+    }
+
+    pub fn serve(&self, req: &Request) -> Response {
+        if req.method != "GET" && req.method != "HEAD" {
+            return Response::text(405, "Method Not Allowed\n").with_header("Allow", "GET, HEAD");
+        }
+
+        let path = match self.resolve(&req.path) {
+            Some(path) => path,
+            None => return self.not_found(),
+        };
+
+        let response = match self.load(&path) {
+            Ok(file) => self.respond(req, &path, &file),
+            Err(e) => Err(e),
+        };
+
+        match response {
+            Ok(mut response) => {
+                if let Some(cache_control) = &self.cache_control {
+                    response
+                        .headers
+                        .insert("Cache-Control", cache_control.clone());
+                }
+                response
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.not_found(),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                Response::text(500, "Internal Server Error\n")
+            }
+        }
+    }
+
+    fn respond(&self, req: &Request, path: &Path, file: &FileEntry) -> io::Result<Response> {
+        let mut response = if is_not_modified(req, file) {
+            Response::new(304)
+        } else {
+            match req.header("Range").filter(|_| if_range_matches(req, file)) {
+                Some(range) => self.partial(path, file, range)?,
+                None => Response::new(200)
+                    .with_header("Content-Type", content_type(path))
+                    .with_stream(file.open(path, 0, file.len)?, file.len),
+            }
+        };
+
+        response.headers.insert("Accept-Ranges", "bytes");
+        response.headers.insert("ETag", file.etag.clone());
+        response
+            .headers
+            .insert("Last-Modified", file.last_modified.to_string());
+        Ok(response)
+    }
+
+    // 响应 Range 请求：单个区间直接返回，多个区间用 multipart/byteranges
+    fn partial(&self, path: &Path, file: &FileEntry, range: &str) -> io::Result<Response> {
+        let content_type = content_type(path);
+
+        let ranges = match parse_range(range, file.len) {
+            Ok(ranges) => ranges,
+            Err(RangeError::Unsatisfiable) => {
+                return Ok(Response::new(416)
+                    .with_header("Content-Range", format!("bytes */{}", file.len)));
+            }
+            Err(RangeError::Invalid) => {
+                return Ok(Response::new(200)
+                    .with_header("Content-Type", content_type)
+                    .with_stream(file.open(path, 0, file.len)?, file.len));
+            }
+        };
+
+        if let [range] = ranges[..] {
+            return Ok(Response::new(206)
+                .with_header("Content-Type", content_type)
+                .with_header("Content-Range", range.content_range(file.len))
+                .with_stream(file.open(path, range.start, range.len())?, range.len()));
+        }
+
+        let multipart = MultipartRanges::new(&ranges, file.len, content_type);
+        let multipart_type = multipart.content_type();
+        let len = multipart.content_length();
+        let reader = multipart.into_reader(|range| file.open(path, range.start, range.len()))?;
+
+        Ok(Response::new(206)
+            .with_header("Content-Type", multipart_type)
+            .with_stream(reader, len))
+    }
+
+    // 把请求路径映射到根目录下的文件，拒绝 `..` 之类跳出根目录的路径
+    fn resolve(&self, request_path: &str) -> Option<PathBuf> {
+        let decoded = percent_decode(request_path)?;
+        let mut path = self.root.clone();
+
+        for component in Path::new(decoded.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+
+        if path.is_dir() {
+            path.push(&self.index);
+        }
+        Some(path)
+    }
+
+    // 小文件放进缓存，其余文件在响应时从磁盘流式读取
+    fn load(&self, path: &Path) -> io::Result<Arc<FileEntry>> {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_file() {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        }
+        let modified = metadata.modified()?;
+        let len = metadata.len();
+
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return Ok(Arc::new(FileEntry::new(modified, len, None))),
+        };
+
+        if let Some(file) = cache.lock().unwrap().get(path, modified, len) {
+            return Ok(file);
+        }
+        if len > cache.lock().unwrap().max_bytes as u64 {
+            return Ok(Arc::new(FileEntry::new(modified, len, None)));
+        }
+
+        // 读文件时不持有锁
+        let contents = Arc::from(fs::read(path)?);
+        let file = Arc::new(FileEntry::new(modified, len, Some(contents)));
+        cache
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), Arc::clone(&file));
+        Ok(file)
+    }
+
+    fn not_found(&self) -> Response {
+        let page = self
+            .not_found_page
+            .as_ref()
+            .and_then(|name| fs::read(self.root.join(name)).ok());
+
+        match page {
+            Some(contents) => Response::html(404, contents),
+            None => Response::text(404, "Not Found\n"),
+        }
+    }
+}
+
+impl Handler for StaticFiles {
+    fn handle(&self, req: &Request) -> Response {
+        self.serve(req)
+    }
+}
+
+struct FileEntry {
+    // 只有放进缓存的文件才有内容
+    contents: Option<Arc<[u8]>>,
+    modified: SystemTime,
+    len: u64,
+    etag: String,
+    last_modified: HttpDate,
+}
+
+impl FileEntry {
+    fn new(modified: SystemTime, len: u64, contents: Option<Arc<[u8]>>) -> FileEntry {
+        // ETag 由修改时间和文件大小组成，不需要对内容做哈希
+        let nanos = modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let etag = format!("\"{:x}-{:x}\"", nanos, len);
+
+        FileEntry {
+            contents,
+            modified,
+            len,
+            etag,
+            last_modified: HttpDate::from(modified),
+        }
+    }
+
+    // 打开从 start 开始、长度为 len 的一段内容
+    fn open(&self, path: &Path, start: u64, len: u64) -> io::Result<Box<dyn Read + Send>> {
+        match &self.contents {
+            Some(contents) => {
+                let mut cursor = Cursor::new(Arc::clone(contents));
+                cursor.set_position(start);
+                Ok(Box::new(cursor.take(len)))
+            }
+            None => {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(start))?;
+                Ok(Box::new(file.take(len)))
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.contents.as_ref().map_or(0, |contents| contents.len())
+    }
+}
+
+// If-None-Match 优先于 If-Modified-Since
+fn is_not_modified(req: &Request, file: &FileEntry) -> bool {
+    if let Some(if_none_match) = req.header("If-None-Match") {
+        return if_none_match.split(',').map(str::trim).any(|tag| {
+            tag == "*" || tag.trim_start_matches("W/") == file.etag.trim_start_matches("W/")
+        });
+    }
+
+    match req.header("If-Modified-Since").and_then(HttpDate::parse) {
+        Some(since) => file.last_modified <= since,
+        None => false,
+    }
+}
+
+// If-Range 只接受强 ETag 或精确相同的修改时间，不匹配时忽略 Range
+fn if_range_matches(req: &Request, file: &FileEntry) -> bool {
+    match req.header("If-Range") {
+        None => true,
+        Some(tag) if tag.starts_with('"') => tag == file.etag,
+        Some(date) => HttpDate::parse(date) == Some(file.last_modified),
+    }
+}
+
+// 按字节数限制大小的 LRU 文件缓存
+struct FileCache {
+    max_bytes: usize,
+    used_bytes: usize,
+    tick: u64,
+    entries: HashMap<PathBuf, (Arc<FileEntry>, u64)>,
+}
+
+impl FileCache {
+    fn new(max_bytes: usize) -> FileCache {
+        FileCache {
+            max_bytes,
+            used_bytes: 0,
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    // 修改时间或大小变化的条目视为失效
+    fn get(&mut self, path: &Path, modified: SystemTime, len: u64) -> Option<Arc<FileEntry>> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        let (file, last_used) = self.entries.get_mut(path)?;
+        if file.modified == modified && file.len == len {
+            *last_used = tick;
+            return Some(Arc::clone(file));
+        }
+
+        self.remove(path);
+        None
+    }
+
+    fn insert(&mut self, path: PathBuf, file: Arc<FileEntry>) {
+        let size = file.size();
+        if size > self.max_bytes {
+            return;
+        }
+
+        self.remove(&path);
+        while self.used_bytes + size > self.max_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(path, _)| path.clone());
+            match oldest {
+                Some(oldest) => self.remove(&oldest),
+                None => break,
+            }
+        }
+
+        self.tick += 1;
+        self.used_bytes += size;
+        self.entries.insert(path, (file, self.tick));
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some((file, _)) = self.entries.remove(path) {
+            self.used_bytes -= file.size();
+        }
+    }
+}
+
+// 根据扩展名推断 Content-Type
+pub fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("ico") => "image/x-icon",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
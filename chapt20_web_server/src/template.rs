@@ -0,0 +1,651 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::response::Response;
+
+// 一个很小的模板引擎
+//
+// {{ name }}              输出变量，会做 HTML 转义
+// {{ name | raw }}        原样输出
+// {% if name %}...{% else %}...{% endif %}
+// {% if not name %}...{% endif %}
+// {% for item in items %}...{% endfor %}   循环中可用 loop.index / loop.first / loop.last
+// {% include "header.html" %}
+// {% extends "layout.html" %} 配合 {% block name %}...{% endblock %} 使用布局
+// {# 注释 #}
+
+// include 和 extends 的最大嵌套层数，防止模板互相引用导致死循环
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    // 空字符串、空列表、0、false 和 Null 都视为假
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Int(n) => *n != 0,
+            Value::Str(s) => !s.is_empty(),
+            Value::List(items) => !items.is_empty(),
+            Value::Map(map) => !map.is_empty(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+            Value::Map(_) => write!(f, "[object]"),
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Str(s)
+    }
+}
+
+impl From<&String> for Value {
+    fn from(s: &String) -> Value {
+        Value::Str(s.clone())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Int(n)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Value {
+        Value::Int(n as i64)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Value {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl From<Context> for Value {
+    fn from(context: Context) -> Value {
+        Value::Map(context.values)
+    }
+}
+
+// 渲染模板时使用的数据
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    values: BTreeMap<String, Value>,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context {
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: &str, value: impl Into<Value>) {
+        self.values.insert(key.to_string(), value.into());
+    }
+
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Context {
+        self.insert(key, value);
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateError {
+    pub name: String,
+    pub message: String,
+}
+
+impl TemplateError {
+    fn new(name: &str, message: impl Into<String>) -> TemplateError {
+        TemplateError {
+            name: name.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "template {}: {}", self.name, self.message)
+    }
+}
+
+impl Error for TemplateError {}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var {
+        path: Vec<String>,
+        raw: bool,
+    },
+    If {
+        path: Vec<String>,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    For {
+        var: String,
+        path: Vec<String>,
+        body: Vec<Node>,
+    },
+    Include(String),
+    Block {
+        name: String,
+        body: Vec<Node>,
+    },
+}
+
+// 解析后的模板
+#[derive(Debug)]
+pub struct Template {
+    name: String,
+    extends: Option<String>,
+    nodes: Vec<Node>,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Var(&'a str),
+    Tag(&'a str),
+}
+
+impl Template {
+    pub fn parse(name: &str, source: &str) -> Result<Template, TemplateError> {
+        let tokens = tokenize(name, source)?;
+        let mut parser = Parser {
+            name,
+            tokens: tokens.into_iter(),
+            extends: None,
+        };
+        let (nodes, end) = parser.parse_nodes()?;
+        if let Some(tag) = end {
+            return Err(TemplateError::new(
+                name,
+                format!("unexpected {{% {} %}}", tag),
+            ));
+        }
+
+        Ok(Template {
+            name: name.to_string(),
+            extends: parser.extends,
+            nodes,
+        })
+    }
+}
+
+fn tokenize<'a>(name: &str, mut source: &'a str) -> Result<Vec<Token<'a>>, TemplateError> {
+    let mut tokens = Vec::new();
+
+    while !source.is_empty() {
+        let start = match source.find('{') {
+            Some(i) => i,
+            None => {
+                tokens.push(Token::Text(source));
+                break;
+            }
+        };
+
+        let close = match source[start..].chars().nth(1) {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => {
+                tokens.push(Token::Text(&source[..start + 1]));
+                source = &source[start + 1..];
+                continue;
+            }
+        };
+
+        if start > 0 {
+            tokens.push(Token::Text(&source[..start]));
+        }
+        let rest = &source[start + 2..];
+        let end = rest
+            .find(close)
+            .ok_or_else(|| TemplateError::new(name, format!("unclosed `{}`", close)))?;
+        let inner = rest[..end].trim();
+
+        match close {
+            "}}" => tokens.push(Token::Var(inner)),
+            "%}" => tokens.push(Token::Tag(inner)),
+            _ => {}
+        }
+        source = &rest[end + 2..];
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a, I> {
+    name: &'a str,
+    tokens: I,
+    extends: Option<String>,
+}
+
+impl<'a, 's, I> Parser<'a, I>
+where
+    I: Iterator<Item = Token<'s>>,
+{
+    // 解析到结束标签（endif / else / endfor / endblock）或者模板末尾
+    fn parse_nodes(&mut self) -> Result<(Vec<Node>, Option<&'s str>), TemplateError> {
+        let mut nodes = Vec::new();
+
+        while let Some(token) = self.tokens.next() {
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text.to_string())),
+                Token::Var(expr) => {
+                    let (expr, raw) = match expr.split_once('|') {
+                        Some((expr, filter)) if filter.trim() == "raw" => (expr, true),
+                        Some(_) => return Err(self.error(format!("unknown filter in `{}`", expr))),
+                        None => (expr, false),
+                    };
+                    nodes.push(Node::Var {
+                        path: self.path(expr)?,
+                        raw,
+                    });
+                }
+                Token::Tag(tag) => {
+                    let mut words = tag.split_whitespace();
+                    match words.next() {
+                        Some("if") => nodes.push(self.parse_if(tag)?),
+                        Some("for") => nodes.push(self.parse_for(tag)?),
+                        Some("include") => nodes.push(Node::Include(self.quoted(tag, "include")?)),
+                        Some("extends") => self.extends = Some(self.quoted(tag, "extends")?),
+                        Some("block") => {
+                            let name = match (words.next(), words.next()) {
+                                (Some(name), None) => name.to_string(),
+                                _ => return Err(self.error(format!("bad tag `{}`", tag))),
+                            };
+                            let body = self.parse_until(&["endblock"])?.0;
+                            nodes.push(Node::Block { name, body });
+                        }
+                        Some("else" | "endif" | "endfor" | "endblock") => {
+                            return Ok((nodes, Some(tag)));
+                        }
+                        _ => return Err(self.error(format!("unknown tag `{}`", tag))),
+                    }
+                }
+            }
+        }
+
+        Ok((nodes, None))
+    }
+
+    fn parse_until(&mut self, ends: &[&str]) -> Result<(Vec<Node>, &'s str), TemplateError> {
+        match self.parse_nodes()? {
+            (nodes, Some(end)) if ends.contains(&end) => Ok((nodes, end)),
+            (_, Some(end)) => Err(self.error(format!("unexpected {{% {} %}}", end))),
+            (_, None) => Err(self.error(format!("missing {{% {} %}}", ends[ends.len() - 1]))),
+        }
+    }
+
+    fn parse_if(&mut self, tag: &str) -> Result<Node, TemplateError> {
+        let words: Vec<&str> = tag.split_whitespace().collect();
+        let (negate, expr) = match words[..] {
+            ["if", "not", expr] => (true, expr),
+            ["if", expr] => (false, expr),
+            _ => return Err(self.error(format!("bad tag `{}`", tag))),
+        };
+        let path = self.path(expr)?;
+
+        let (then, end) = self.parse_until(&["else", "endif"])?;
+        let otherwise = if end == "else" {
+            self.parse_until(&["endif"])?.0
+        } else {
+            Vec::new()
+        };
+
+        Ok(Node::If {
+            path,
+            negate,
+            then,
+            otherwise,
+        })
+    }
+
+    fn parse_for(&mut self, tag: &str) -> Result<Node, TemplateError> {
+        let words: Vec<&str> = tag.split_whitespace().collect();
+        let (var, expr) = match words[..] {
+            ["for", var, "in", expr] => (var, expr),
+            _ => return Err(self.error(format!("bad tag `{}`", tag))),
+        };
+        let path = self.path(expr)?;
+        let body = self.parse_until(&["endfor"])?.0;
+
+        Ok(Node::For {
+            var: var.to_string(),
+            path,
+            body,
+        })
+    }
+
+    // 形如 `user.name` 的变量路径
+    fn path(&self, expr: &str) -> Result<Vec<String>, TemplateError> {
+        let valid = |part: &str| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+
+        let parts: Vec<&str> = expr.trim().split('.').collect();
+        if !parts.iter().all(|part| valid(part)) {
+            return Err(self.error(format!("bad variable `{}`", expr)));
+        }
+        Ok(parts.into_iter().map(str::to_string).collect())
+    }
+
+    fn quoted(&self, tag: &str, keyword: &str) -> Result<String, TemplateError> {
+        tag[keyword.len()..]
+            .trim()
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .map(str::to_string)
+            .ok_or_else(|| self.error(format!("bad tag `{}`", tag)))
+    }
+
+    fn error(&self, message: String) -> TemplateError {
+        TemplateError::new(self.name, message)
+    }
+}
+
+// 从目录中加载模板并缓存
+// 开启热加载后每次渲染都会检查文件修改时间，文件变化后重新解析
+pub struct Templates {
+    dir: PathBuf,
+    hot_reload: bool,
+    cache: Mutex<HashMap<String, (SystemTime, Arc<Template>)>>,
+}
+
+impl Templates {
+    pub fn new(dir: impl Into<PathBuf>) -> Templates {
+        Templates {
+            dir: dir.into(),
+            hot_reload: false,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 开发时打开，修改模板文件后不需要重启服务器。
+    pub fn hot_reload(mut self, enabled: bool) -> Templates {
+        self.hot_reload = enabled;
+        self
+    }
+
+    pub fn render(&self, name: &str, context: &Context) -> Result<String, TemplateError> {
+        let mut out = String::new();
+        let mut renderer = Renderer {
+            templates: self,
+            blocks: HashMap::new(),
+            locals: Vec::new(),
+            root: context,
+        };
+        renderer.render_template(name, &mut out, 0)?;
+        Ok(out)
+    }
+
+    /// 渲染模板并生成 HTML 响应，渲染失败时返回 500。
+    pub fn render_response(&self, status: u16, name: &str, context: &Context) -> Response {
+        match self.render(name, context) {
+            Ok(html) => Response::html(status, html),
+            Err(e) => {
+                eprintln!("Failed to render {}: {}", name, e);
+                Response::text(500, "Internal Server Error\n")
+            }
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<Arc<Template>, TemplateError> {
+        let path = self.path(name)?;
+        let io_error = |e: std::io::Error| TemplateError::new(name, e.to_string());
+
+        let cached = self.cache.lock().unwrap().get(name).cloned();
+        let modified = match (cached, self.hot_reload) {
+            (Some((_, template)), false) => return Ok(template),
+            (Some((modified, template)), true) => {
+                let current = fs::metadata(&path).and_then(|m| m.modified());
+                match current.map_err(io_error)? {
+                    current if current == modified => return Ok(template),
+                    current => current,
+                }
+            }
+            (None, _) => fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .map_err(io_error)?,
+        };
+
+        let source = fs::read_to_string(&path).map_err(io_error)?;
+        let template = Arc::new(Template::parse(name, &source)?);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), (modified, Arc::clone(&template)));
+        Ok(template)
+    }
+
+    // 模板名不能跳出模板目录
+    fn path(&self, name: &str) -> Result<PathBuf, TemplateError> {
+        let relative = Path::new(name);
+        if relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            Ok(self.dir.join(relative))
+        } else {
+            Err(TemplateError::new(name, "invalid template name"))
+        }
+    }
+}
+
+struct Renderer<'a> {
+    templates: &'a Templates,
+    // 子模板覆盖的 block，越靠近子模板的优先
+    blocks: HashMap<String, Arc<Template>>,
+    // for 循环引入的局部变量
+    locals: Vec<(String, Value)>,
+    root: &'a Context,
+}
+
+impl Renderer<'_> {
+    fn render_template(
+        &mut self,
+        name: &str,
+        out: &mut String,
+        depth: usize,
+    ) -> Result<(), TemplateError> {
+        if depth > MAX_DEPTH {
+            return Err(TemplateError::new(name, "templates nested too deeply"));
+        }
+        let template = self.templates.get(name)?;
+
+        match &template.extends {
+            Some(parent) => {
+                // 记录本模板定义的 block，然后渲染布局模板
+                for node in &template.nodes {
+                    if let Node::Block { name, .. } = node {
+                        self.blocks
+                            .entry(name.clone())
+                            .or_insert_with(|| Arc::clone(&template));
+                    }
+                }
+                self.render_template(parent, out, depth + 1)
+            }
+            None => self.render_nodes(&template, &template.nodes, out, depth),
+        }
+    }
+
+    fn render_nodes(
+        &mut self,
+        template: &Template,
+        nodes: &[Node],
+        out: &mut String,
+        depth: usize,
+    ) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var { path, raw } => {
+                    let value = self.lookup(path).to_string();
+                    if *raw {
+                        out.push_str(&value);
+                    } else {
+                        out.push_str(&escape_html(&value));
+                    }
+                }
+                Node::If {
+                    path,
+                    negate,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if self.lookup(path).is_truthy() != *negate {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.render_nodes(template, branch, out, depth)?;
+                }
+                Node::For { var, path, body } => {
+                    let items = match self.lookup(path) {
+                        Value::List(items) => items,
+                        Value::Null => Vec::new(),
+                        _ => {
+                            return Err(TemplateError::new(
+                                &template.name,
+                                format!("`{}` is not a list", path.join(".")),
+                            ))
+                        }
+                    };
+
+                    let count = items.len();
+                    for (i, item) in items.into_iter().enumerate() {
+                        let info = Context::new()
+                            .with("index", i + 1)
+                            .with("first", i == 0)
+                            .with("last", i + 1 == count);
+                        self.locals.push(("loop".to_string(), info.into()));
+                        self.locals.push((var.clone(), item));
+                        let result = self.render_nodes(template, body, out, depth);
+                        self.locals.truncate(self.locals.len() - 2);
+                        result?;
+                    }
+                }
+                Node::Include(name) => self.render_template(name, out, depth + 1)?,
+                Node::Block { name, body } => match self.blocks.get(name).cloned() {
+                    Some(child) => {
+                        let child_body = child.nodes.iter().find_map(|node| match node {
+                            Node::Block { name: n, body } if n == name => Some(body),
+                            _ => None,
+                        });
+                        if let Some(child_body) = child_body {
+                            self.render_nodes(&child, child_body, out, depth)?;
+                        }
+                    }
+                    None => self.render_nodes(template, body, out, depth)?,
+                },
+            }
+        }
+        Ok(())
+    }
+
+    // 先找循环变量，再找渲染时传入的数据，找不到时返回 Null
+    fn lookup(&self, path: &[String]) -> Value {
+        let first = &path[0];
+        let value = self
+            .locals
+            .iter()
+            .rev()
+            .find(|(name, _)| name == first)
+            .map(|(_, value)| value)
+            .or_else(|| self.root.values.get(first));
+
+        let mut value = match value {
+            Some(value) => value,
+            None => return Value::Null,
+        };
+        for key in &path[1..] {
+            value = match value.get(key) {
+                Some(value) => value,
+                None => return Value::Null,
+            };
+        }
+        value.clone()
+    }
+}
+
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
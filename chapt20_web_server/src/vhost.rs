@@ -0,0 +1,115 @@
+use std::{io::Read, path::PathBuf};
+
+use crate::{request::Request, response::Response, server::Handler, static_files::StaticFiles};
+
+// 一个虚拟主机的匹配规则
+enum Pattern {
+    // example.com
+    Exact(String),
+    // *.example.com，匹配任意层子域名，不匹配 example.com 本身
+    Wildcard(String),
+}
+
+// 按 Host header 把请求分给不同的站点
+#[derive(Default)]
+pub struct VirtualHosts {
+    hosts: Vec<(Pattern, Box<dyn Handler>)>,
+    default: Option<Box<dyn Handler>>,
+}
+
+impl VirtualHosts {
+    pub fn new() -> VirtualHosts {
+        VirtualHosts::default()
+    }
+
+    /// 把 `pattern` 的请求交给 `handler`，`pattern` 可以是 `example.com` 或 `*.example.com`。
+    pub fn host(mut self, pattern: &str, handler: impl Handler) -> VirtualHosts {
+        let pattern = normalize(pattern);
+        let pattern = match pattern.strip_prefix("*.") {
+            Some(suffix) => Pattern::Wildcard(format!(".{}", suffix)),
+            None => Pattern::Exact(pattern),
+        };
+        self.hosts.push((pattern, Box::new(handler)));
+        self
+    }
+
+    /// 用 `root` 目录下的静态文件作为 `pattern` 的站点。
+    pub fn root(self, pattern: &str, root: impl Into<PathBuf>) -> VirtualHosts {
+        self.host(pattern, StaticFiles::new(root))
+    }
+
+    /// 没有匹配的主机或请求没有 `Host` 时使用的站点。
+    pub fn default_host(mut self, handler: impl Handler) -> VirtualHosts {
+        self.default = Some(Box::new(handler));
+        self
+    }
+
+    // 精确匹配优先，其次是后缀最长的通配符
+    fn find(&self, host: &str) -> Option<&dyn Handler> {
+        let exact = self.hosts.iter().find(|(pattern, _)| match pattern {
+            Pattern::Exact(name) => name == host,
+            Pattern::Wildcard(_) => false,
+        });
+        let found = exact.or_else(|| {
+            self.hosts
+                .iter()
+                .filter(|(pattern, _)| match pattern {
+                    Pattern::Wildcard(suffix) => {
+                        host.len() > suffix.len() && host.ends_with(suffix.as_str())
+                    }
+                    Pattern::Exact(_) => false,
+                })
+                .max_by_key(|(pattern, _)| match pattern {
+                    Pattern::Wildcard(suffix) => suffix.len(),
+                    Pattern::Exact(_) => 0,
+                })
+        });
+        found.map(|(_, handler)| handler.as_ref())
+    }
+}
+
+impl VirtualHosts {
+    fn select(&self, req: &Request) -> Option<&dyn Handler> {
+        req.header("Host")
+            .and_then(|host| self.find(&normalize(host)))
+            .or(self.default.as_deref())
+    }
+}
+
+impl Handler for VirtualHosts {
+    fn handle(&self, req: &Request) -> Response {
+        match self.select(req) {
+            Some(handler) => handler.handle(req),
+            None => Response::text(404, "Unknown host\n"),
+        }
+    }
+
+    fn streams_body(&self, req: &Request) -> bool {
+        self.select(req)
+            .is_some_and(|handler| handler.streams_body(req))
+    }
+
+    fn handle_with_body(&self, req: &Request, body: &mut dyn Read) -> Response {
+        match self.select(req) {
+            Some(handler) => handler.handle_with_body(req, body),
+            None => Response::text(404, "Unknown host\n"),
+        }
+    }
+}
+
+// 去掉端口和末尾的点，转为小写，`[::1]:8080` 变为 `[::1]`
+fn normalize(host: &str) -> String {
+    let host = host.trim();
+    let host = if host.starts_with('[') {
+        match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        }
+    } else {
+        match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+            _ => host,
+        }
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
@@ -0,0 +1,467 @@
+use std::{
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::Rng;
+use sha1::{Digest, Sha1};
+
+use crate::{request::Request, response::Response, server::Upgraded};
+
+// RFC 6455 握手时拼在 Sec-WebSocket-Key 后面的固定 GUID
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// 单条消息（合并分片后）的默认大小上限
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// [`upgrade`] 给连接设置的读超时，超过这个时间没有收到任何帧时 `recv` 返回错误，
+/// 连接不会一直占着工作线程。需要更长空闲时间的应用可以定时发送 Ping。
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// 关闭码
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_UNSUPPORTED: u16 = 1003;
+pub const CLOSE_INVALID_PAYLOAD: u16 = 1007;
+pub const CLOSE_TOO_BIG: u16 = 1009;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    fn from_u8(op: u8) -> Option<OpCode> {
+        match op {
+            0x0 => Some(OpCode::Continuation),
+            0x1 => Some(OpCode::Text),
+            0x2 => Some(OpCode::Binary),
+            0x8 => Some(OpCode::Close),
+            0x9 => Some(OpCode::Ping),
+            0xA => Some(OpCode::Pong),
+            _ => None,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            OpCode::Continuation => 0x0,
+            OpCode::Text => 0x1,
+            OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
+            OpCode::Ping => 0x9,
+            OpCode::Pong => 0xA,
+        }
+    }
+
+    fn is_control(self) -> bool {
+        matches!(self, OpCode::Close | OpCode::Ping | OpCode::Pong)
+    }
+}
+
+// 一个 WebSocket 帧
+//  0                   1                   2                   3
+// +-+-+-+-+-------+-+-------------+-------------------------------+
+// |F|R|R|R| opcode|M| Payload len |    Extended payload length    |
+// |I|S|S|S|  (4)  |A|     (7)     |             (16/64)           |
+// |N|V|V|V|       |S|             |                               |
+// +-+-+-+-+-------+-+-------------+-------------------------------+
+// |     Masking-key (0 或 4 字节)  |          Payload Data         |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: OpCode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(opcode: OpCode, payload: impl Into<Vec<u8>>) -> Frame {
+        Frame {
+            fin: true,
+            opcode,
+            payload: payload.into(),
+        }
+    }
+
+    /// 读取一帧并去掉掩码。
+    ///
+    /// # Errors
+    ///
+    /// 帧格式错误时返回 `InvalidData`，payload 超过 `max_size` 时返回 `FileTooLarge`。
+    pub fn read_from(reader: &mut impl Read, max_size: usize) -> io::Result<Frame> {
+        read_frame(reader, max_size).map(|(frame, _)| frame)
+    }
+
+    /// 写出一帧，客户端发送的帧必须带 `mask`。
+    pub fn write_to(&self, writer: &mut impl Write, mask: Option<[u8; 4]>) -> io::Result<()> {
+        let mut head = Vec::with_capacity(14);
+        head.push(if self.fin { 0x80 } else { 0 } | self.opcode.as_u8());
+
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        let len = self.payload.len();
+        if len < 126 {
+            head.push(mask_bit | len as u8);
+        } else if len <= u16::MAX as usize {
+            head.push(mask_bit | 126);
+            head.extend_from_slice(&(len as u16).to_be_bytes());
+        } else {
+            head.push(mask_bit | 127);
+            head.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+
+        match mask {
+            Some(mask) => {
+                head.extend_from_slice(&mask);
+                let mut payload = self.payload.clone();
+                apply_mask(&mut payload, mask);
+                writer.write_all(&head)?;
+                writer.write_all(&payload)?;
+            }
+            None => {
+                writer.write_all(&head)?;
+                writer.write_all(&self.payload)?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+// 读取一帧，同时返回帧是否带掩码
+fn read_frame(reader: &mut impl Read, max_size: usize) -> io::Result<(Frame, bool)> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head)?;
+
+    let fin = head[0] & 0x80 != 0;
+    if head[0] & 0x70 != 0 {
+        return Err(invalid_data("reserved bits set"));
+    }
+    let opcode = OpCode::from_u8(head[0] & 0x0F).ok_or_else(|| invalid_data("bad opcode"))?;
+    let masked = head[1] & 0x80 != 0;
+
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+
+    // 控制帧不能分片，payload 不超过 125 字节
+    if opcode.is_control() && (!fin || len > 125) {
+        return Err(invalid_data("bad control frame"));
+    }
+    if len > max_size as u64 {
+        return Err(io::Error::from(io::ErrorKind::FileTooLarge));
+    }
+
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        apply_mask(&mut payload, mask);
+    }
+
+    Ok((
+        Frame {
+            fin,
+            opcode,
+            payload,
+        },
+        masked,
+    ))
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    // 关闭码和原因
+    Close(Option<(u16, String)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Server,
+    Client,
+}
+
+// 建立好的 WebSocket 连接
+// recv 会合并分片、自动回复 Ping，并完成关闭握手
+pub struct WebSocket<S> {
+    stream: S,
+    role: Role,
+    max_message_size: usize,
+    closed: bool,
+    // 尚未收完的分片消息，中间可能穿插控制帧
+    fragments: Option<(OpCode, Vec<u8>)>,
+}
+
+impl<S: Read + Write> WebSocket<S> {
+    pub fn new(stream: S, role: Role) -> WebSocket<S> {
+        WebSocket {
+            stream,
+            role,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            closed: false,
+            fragments: None,
+        }
+    }
+
+    pub fn max_message_size(mut self, bytes: usize) -> WebSocket<S> {
+        self.max_message_size = bytes;
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// 收到对方的 Close 或者自己发送了 Close 后为 true。
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// 接收下一条消息。
+    ///
+    /// Ping 会自动回复 Pong 后再返回给调用者。收到 Close 时会回复 Close，
+    /// 之后调用者应当停止收发。协议错误时会先发送对应关闭码再返回错误。
+    pub fn recv(&mut self) -> io::Result<Message> {
+        loop {
+            let frame = match read_frame(&mut self.stream, self.max_message_size) {
+                // 客户端发出的帧必须带掩码，服务端发出的帧不能带掩码
+                Ok((frame, masked)) if masked == (self.role == Role::Server) => frame,
+                Ok(_) => return Err(self.fail(invalid_data("bad frame masking"))),
+                Err(e) => return Err(self.fail(e)),
+            };
+
+            match frame.opcode {
+                OpCode::Ping => {
+                    self.send_frame(Frame::new(OpCode::Pong, frame.payload.clone()))?;
+                    return Ok(Message::Ping(frame.payload));
+                }
+                OpCode::Pong => return Ok(Message::Pong(frame.payload)),
+                // 关闭码是 2 字节，只有 1 字节的 payload 是协议错误
+                OpCode::Close if frame.payload.len() == 1 => {
+                    return Err(self.fail(invalid_data("truncated close code")));
+                }
+                OpCode::Close => {
+                    let close = parse_close(&frame.payload);
+                    if !self.closed {
+                        let code = close.as_ref().map_or(CLOSE_NORMAL, |(code, _)| *code);
+                        self.send_frame(Frame::new(OpCode::Close, code.to_be_bytes()))?;
+                        self.closed = true;
+                    }
+                    return Ok(Message::Close(close));
+                }
+                OpCode::Text | OpCode::Binary if self.fragments.is_none() => {
+                    if frame.fin {
+                        return self.message(frame.opcode, frame.payload);
+                    }
+                    self.fragments = Some((frame.opcode, frame.payload));
+                }
+                OpCode::Continuation if self.fragments.is_some() => {
+                    let (opcode, mut payload) = self.fragments.take().unwrap();
+                    if payload.len() + frame.payload.len() > self.max_message_size {
+                        let e = io::Error::from(io::ErrorKind::FileTooLarge);
+                        return Err(self.fail(e));
+                    }
+                    payload.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return self.message(opcode, payload);
+                    }
+                    self.fragments = Some((opcode, payload));
+                }
+                _ => return Err(self.fail(invalid_data("unexpected frame"))),
+            }
+        }
+    }
+
+    pub fn send(&mut self, message: Message) -> io::Result<()> {
+        let frame = match message {
+            Message::Text(text) => Frame::new(OpCode::Text, text.into_bytes()),
+            Message::Binary(bytes) => Frame::new(OpCode::Binary, bytes),
+            Message::Ping(bytes) => Frame::new(OpCode::Ping, bytes),
+            Message::Pong(bytes) => Frame::new(OpCode::Pong, bytes),
+            Message::Close(close) => {
+                let (code, reason) = close.unwrap_or((CLOSE_NORMAL, String::new()));
+                return self.close(code, &reason);
+            }
+        };
+        self.send_frame(frame)
+    }
+
+    /// 发送 Close 帧，之后等待对方回复 Close。
+    pub fn close(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        payload.truncate(125);
+        self.send_frame(Frame::new(OpCode::Close, payload))
+    }
+
+    /// 直接发送一帧，可以用来发送分片消息。
+    pub fn send_frame(&mut self, frame: Frame) -> io::Result<()> {
+        let mask = match self.role {
+            Role::Client => Some(rand::thread_rng().gen()),
+            Role::Server => None,
+        };
+        frame.write_to(&mut self.stream, mask)
+    }
+
+    fn message(&mut self, opcode: OpCode, payload: Vec<u8>) -> io::Result<Message> {
+        match opcode {
+            OpCode::Text => match String::from_utf8(payload) {
+                Ok(text) => Ok(Message::Text(text)),
+                Err(_) => {
+                    let _ = self.close(CLOSE_INVALID_PAYLOAD, "invalid UTF-8");
+                    Err(invalid_data("invalid UTF-8 in text message"))
+                }
+            },
+            _ => Ok(Message::Binary(payload)),
+        }
+    }
+
+    // 协议错误时发送对应的关闭码
+    fn fail(&mut self, e: io::Error) -> io::Error {
+        let code = match e.kind() {
+            io::ErrorKind::InvalidData => CLOSE_PROTOCOL_ERROR,
+            io::ErrorKind::FileTooLarge => CLOSE_TOO_BIG,
+            _ => return e,
+        };
+        let _ = self.close(code, "");
+        e
+    }
+}
+
+fn parse_close(payload: &[u8]) -> Option<(u16, String)> {
+    if payload.len() < 2 {
+        return None;
+    }
+    let code = u16::from_be_bytes([payload[0], payload[1]]);
+    let reason = String::from_utf8_lossy(&payload[2..]).into_owned();
+    Some((code, reason))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 根据 `Sec-WebSocket-Key` 计算 `Sec-WebSocket-Accept`。
+pub fn accept_key(key: &str) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.trim().as_bytes());
+    sha1.update(GUID.as_bytes());
+    STANDARD.encode(sha1.finalize())
+}
+
+/// 请求是否是 WebSocket 升级请求。
+pub fn is_upgrade_request(req: &Request) -> bool {
+    let has_token = |name: &str, token: &str| {
+        req.headers
+            .get_all(name)
+            .flat_map(|v| v.split(','))
+            .any(|v| v.trim().eq_ignore_ascii_case(token))
+    };
+    req.method == "GET" && has_token("Connection", "upgrade") && has_token("Upgrade", "websocket")
+}
+
+/// 完成握手并把连接交给 `f`，`f` 在线程池的工作线程中运行。
+///
+/// 连接的读超时是 [`IDLE_TIMEOUT`]，可以通过 `ws.get_ref().stream()` 修改。
+/// 不是合法的升级请求时返回 400；版本不是 13 时返回 426。
+pub fn upgrade<F>(req: &Request, f: F) -> Response
+where
+    F: FnOnce(WebSocket<Upgraded>) + Send + 'static,
+{
+    if !is_upgrade_request(req) {
+        return Response::text(400, "Expected a WebSocket upgrade request\n");
+    }
+    if req.header("Sec-WebSocket-Version") != Some("13") {
+        return Response::text(426, "Unsupported WebSocket version\n")
+            .with_header("Sec-WebSocket-Version", "13");
+    }
+
+    // key 是 base64 编码的 16 字节随机数
+    let key = match req.header("Sec-WebSocket-Key") {
+        Some(key) if STANDARD.decode(key.trim()).is_ok_and(|k| k.len() == 16) => key,
+        _ => return Response::text(400, "Bad Sec-WebSocket-Key\n"),
+    };
+
+    Response::new(101)
+        .with_header("Upgrade", "websocket")
+        .with_header("Connection", "Upgrade")
+        .with_header("Sec-WebSocket-Accept", accept_key(key))
+        .with_upgrade(move |upgraded| {
+            // 设置失败时连接已经不可用，recv 会返回错误
+            let _ = upgraded.stream().set_read_timeout(Some(IDLE_TIMEOUT));
+            f(WebSocket::new(upgraded, Role::Server))
+        })
+}
+
+/// 原样返回收到的每条消息，直到对方关闭连接。
+pub fn echo(mut ws: WebSocket<Upgraded>) {
+    loop {
+        let reply = match ws.recv() {
+            Ok(Message::Text(text)) => Message::Text(text),
+            Ok(Message::Binary(bytes)) => Message::Binary(bytes),
+            Ok(Message::Ping(_) | Message::Pong(_)) => continue,
+            Ok(Message::Close(_)) | Err(_) => return,
+        };
+        if ws.send(reply).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_from_rfc() {
+        assert_eq!(
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+            accept_key("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = Frame::new(OpCode::Binary, vec![7; 300]);
+        let mut encoded = Vec::new();
+        frame.write_to(&mut encoded, Some([1, 2, 3, 4])).unwrap();
+        assert_eq!([0x82, 0x80 | 126, 0x01, 0x2C], encoded[..4]);
+
+        let decoded = Frame::read_from(&mut encoded.as_slice(), 1024).unwrap();
+        assert_eq!(frame, decoded);
+    }
+}
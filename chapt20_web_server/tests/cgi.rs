@@ -0,0 +1,168 @@
+#![cfg(unix)]
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chapt20_web_server::{cgi::Cgi, request::Request, response::Response, server::Handler};
+
+mod common;
+
+use common::temp_dir;
+
+fn script(dir: &Path, name: &str, contents: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn body_text(response: Response) -> String {
+    String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+}
+
+fn cgi_dir(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    script(
+        &dir,
+        "env.sh",
+        "#!/bin/sh\n\
+         printf 'Content-Type: text/plain\\r\\nX-Script: env\\r\\n\\r\\n'\n\
+         echo \"method=$REQUEST_METHOD\"\n\
+         echo \"script=$SCRIPT_NAME\"\n\
+         echo \"path_info=$PATH_INFO\"\n\
+         echo \"query=$QUERY_STRING\"\n\
+         echo \"server=$SERVER_NAME:$SERVER_PORT\"\n\
+         echo \"agent=$HTTP_USER_AGENT\"\n\
+         echo \"gateway=$GATEWAY_INTERFACE\"\n",
+    );
+    script(
+        &dir,
+        "echo.sh",
+        "#!/bin/sh\n\
+         echo 'Status: 201 Created'\n\
+         echo \"Content-Type: $CONTENT_TYPE\"\n\
+         echo\n\
+         head -c \"$CONTENT_LENGTH\"\n",
+    );
+    script(
+        &dir,
+        "redirect.sh",
+        "#!/bin/sh\necho 'Location: /elsewhere'\necho\n",
+    );
+    script(
+        &dir,
+        "length.sh",
+        "#!/bin/sh\necho 'Content-Type: text/plain'\necho 'Content-Length: 100'\necho\necho hi\n",
+    );
+    script(
+        &dir,
+        "empty.sh",
+        "#!/bin/sh\necho 'Status: 204 No Content'\necho\necho stray\n",
+    );
+    script(
+        &dir,
+        "interim.sh",
+        "#!/bin/sh\necho 'Status: 103 Early Hints'\necho 'Content-Type: text/plain'\necho\n",
+    );
+    script(&dir, "broken.sh", "#!/bin/sh\necho 'no headers here'\n");
+    script(
+        &dir,
+        "slow.sh",
+        "#!/bin/sh\nsleep 5\necho 'Content-Type: text/plain'\necho\n",
+    );
+    // 后台进程继承了 stdout，脚本退出后 stdout 仍然没有关闭
+    script(
+        &dir,
+        "daemon.sh",
+        "#!/bin/sh\nsleep 5 &\necho 'Content-Type: text/plain'\necho\n",
+    );
+    script(
+        &dir,
+        "large.sh",
+        "#!/bin/sh\necho 'Content-Type: text/plain'\necho\nhead -c 11000000 /dev/zero\n",
+    );
+    dir
+}
+
+#[test]
+fn sets_cgi_environment() {
+    let cgi = Cgi::new("/cgi-bin", cgi_dir("cgi_env"));
+
+    let mut req = Request::new("GET", "/cgi-bin/env.sh/extra/path?a=1&b=2");
+    req.headers.insert("Host", "example.com:8080");
+    req.headers.insert("User-Agent", "test-agent");
+    let response = cgi.handle(&req);
+
+    assert_eq!(200, response.status);
+    assert_eq!(Some("env"), response.headers.get("X-Script"));
+    let body = body_text(response);
+    assert!(body.contains("method=GET\n"));
+    assert!(body.contains("script=/cgi-bin/env.sh\n"));
+    assert!(body.contains("path_info=/extra/path\n"));
+    assert!(body.contains("query=a=1&b=2\n"));
+    assert!(body.contains("server=example.com:8080\n"));
+    assert!(body.contains("agent=test-agent\n"));
+    assert!(body.contains("gateway=CGI/1.1\n"));
+}
+
+#[test]
+fn pipes_body_and_parses_status() {
+    let cgi = Cgi::new("/cgi-bin", cgi_dir("cgi_body"));
+
+    let mut req = Request::new("POST", "/cgi-bin/echo.sh");
+    req.headers.insert("Content-Type", "application/json");
+    req.body = b"{\"name\":\"Ferris\"}".to_vec();
+    let response = cgi.handle(&req);
+
+    assert_eq!(201, response.status);
+    assert_eq!(
+        Some("application/json"),
+        response.headers.get("Content-Type")
+    );
+    assert_eq!("{\"name\":\"Ferris\"}", body_text(response));
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/redirect.sh"));
+    assert_eq!(302, response.status);
+    assert_eq!(Some("/elsewhere"), response.headers.get("Location"));
+
+    // 脚本给的 Content-Length 不可信，按实际输出计算
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/length.sh"));
+    assert!(!response.headers.contains("Content-Length"));
+    assert_eq!("hi\n", body_text(response));
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/empty.sh"));
+    assert_eq!(204, response.status);
+    assert!(response.body.is_empty());
+}
+
+#[test]
+fn reports_script_failures() {
+    let cgi = Cgi::new("/cgi-bin", cgi_dir("cgi_fail")).timeout(Duration::from_millis(200));
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/broken.sh"));
+    assert_eq!(502, response.status);
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/interim.sh"));
+    assert_eq!(502, response.status);
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/slow.sh"));
+    assert_eq!(504, response.status);
+
+    let start = Instant::now();
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/daemon.sh"));
+    assert_eq!(504, response.status);
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/large.sh"));
+    assert_eq!(502, response.status);
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/missing.sh"));
+    assert_eq!(404, response.status);
+
+    let response = cgi.handle(&Request::new("GET", "/cgi-bin/../env.sh"));
+    assert_eq!(404, response.status);
+}
@@ -0,0 +1,168 @@
+use std::{io, io::Cursor, net::SocketAddr, thread, time::Duration};
+
+use chapt20_web_server::{
+    client::Client,
+    request::Request,
+    response::{Body, Response},
+    server::{Handler, Server},
+};
+
+mod common;
+
+use common::spawn_server;
+
+// 开启 keep-alive 的服务器
+fn spawn_keep_alive(handler: impl Handler, idle: Duration) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0", 2, handler)
+        .unwrap()
+        .keep_alive(idle);
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+// 返回客户端的端口，用来判断是否复用了连接
+fn peer_port(req: &Request) -> Response {
+    Response::text(200, req.peer_addr.unwrap().port().to_string())
+}
+
+fn body_text(response: Response) -> String {
+    String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+}
+
+#[test]
+fn reuses_keep_alive_connections() {
+    let addr = spawn_keep_alive(peer_port, Duration::from_secs(5));
+    let client = Client::new();
+    let url = format!("http://{}/", addr);
+
+    let first = body_text(client.get(&url).unwrap());
+    let second = body_text(client.get(&url).unwrap());
+    assert_eq!(first, second);
+
+    let client = Client::new().keep_alive(false);
+    let first = body_text(client.get(&url).unwrap());
+    let second = body_text(client.get(&url).unwrap());
+    assert_ne!(first, second);
+}
+
+#[test]
+fn retries_when_idle_connection_was_closed() {
+    let addr = spawn_keep_alive(peer_port, Duration::from_millis(100));
+    let client = Client::new();
+    let url = format!("http://{}/", addr);
+
+    let first = body_text(client.get(&url).unwrap());
+    thread::sleep(Duration::from_millis(300));
+    let second = body_text(client.get(&url).unwrap());
+    assert_ne!(first, second);
+}
+
+#[test]
+fn decodes_chunked_body() {
+    let addr = spawn_server(|_: &Request| {
+        let mut response = Response::new(200).with_header("Transfer-Encoding", "chunked");
+        response.body = Body::stream(Cursor::new(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"), None);
+        response
+    });
+
+    let response = Client::new().get(&format!("http://{}/", addr)).unwrap();
+    assert_eq!(200, response.status);
+    assert!(!response.headers.contains("Transfer-Encoding"));
+    assert_eq!("hello world", body_text(response));
+}
+
+#[test]
+fn rejects_overflowing_chunk_size() {
+    let addr = spawn_server(|_: &Request| {
+        let mut response = Response::new(200).with_header("Transfer-Encoding", "chunked");
+        response.body = Body::stream(Cursor::new(b"1\r\na\r\nFFFFFFFFFFFFFFFF\r\n"), None);
+        response
+    });
+
+    let err = Client::new().get(&format!("http://{}/", addr)).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+}
+
+#[test]
+fn follows_redirects() {
+    let addr = spawn_server(|req: &Request| match req.path.as_str() {
+        "/old" => Response::new(302).with_header("Location", "/new"),
+        "/form" => Response::new(303).with_header("Location", "/new?done=1"),
+        "/loop" => Response::new(307).with_header("Location", "/loop"),
+        "/docs/old" => Response::new(302).with_header("Location", "new?x=1"),
+        _ => Response::text(
+            200,
+            format!("{} {} {}", req.method, req.path, req.body.len()),
+        ),
+    });
+    let client = Client::new();
+
+    let response = client.get(&format!("http://{}/old", addr)).unwrap();
+    assert_eq!("GET /new 0", body_text(response));
+
+    let response = client
+        .post(&format!("http://{}/form", addr), "text/plain", "data")
+        .unwrap();
+    assert_eq!("GET /new 0", body_text(response));
+
+    // 相对路径按当前路径解析
+    let response = client.get(&format!("http://{}/docs/old", addr)).unwrap();
+    assert_eq!("GET /docs/new 0", body_text(response));
+
+    let response = Client::new()
+        .max_redirects(0)
+        .get(&format!("http://{}/old", addr))
+        .unwrap();
+    assert_eq!(302, response.status);
+
+    let err = client.get(&format!("http://{}/loop", addr)).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+}
+
+#[test]
+fn drops_credentials_on_cross_host_redirect() {
+    let target = spawn_server(|req: &Request| {
+        let header = |name| req.header(name).unwrap_or("-").to_string();
+        Response::text(
+            200,
+            format!(
+                "{} {} {} {}",
+                header("Authorization"),
+                header("Cookie"),
+                header("Proxy-Authorization"),
+                header("X-Trace")
+            ),
+        )
+    });
+    let location = format!("http://{}/landing", target);
+    let origin = spawn_server(move |_: &Request| {
+        Response::new(302).with_header("Location", location.clone())
+    });
+
+    let mut req = Request::new("GET", "/");
+    for (name, value) in [
+        ("Authorization", "Bearer secret"),
+        ("Cookie", "session=1"),
+        ("Proxy-Authorization", "Basic abc"),
+        ("X-Trace", "7"),
+    ] {
+        req.headers.insert(name, value);
+    }
+    let response = Client::new().send(&origin.to_string(), req).unwrap();
+    assert_eq!("- - - 7", body_text(response));
+}
+
+#[test]
+fn times_out_slow_responses() {
+    let addr = spawn_server(|_: &Request| {
+        thread::sleep(Duration::from_millis(500));
+        Response::text(200, "late")
+    });
+
+    let err = Client::new()
+        .timeout(Some(Duration::from_millis(100)))
+        .get(&format!("http://{}/", addr))
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+}
@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+    thread,
+};
+
+use chapt20_web_server::server::{Handler, Server};
+
+// 在系统临时目录下创建一个空目录
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chapt20_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// 在随机端口上启动服务器
+pub fn spawn_server(handler: impl Handler) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0", 2, handler).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+// 发送原始请求并读取完整响应（服务器会关闭连接）
+pub fn send_raw(addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    String::from_utf8_lossy(&response).into_owned()
+}
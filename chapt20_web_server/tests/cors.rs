@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use chapt20_web_server::{
+    cors::Cors, request::Request, response::Response, security::SecurityHeaders, server::Handler,
+};
+
+fn api() -> impl Handler {
+    Cors::new()
+        .allow_origin("https://app.example.com")
+        .allow_methods(&["GET", "PUT"])
+        .allow_headers(&["Content-Type", "X-Token"])
+        .allow_credentials(true)
+        .max_age(Duration::from_secs(600))
+        .wrap(|_: &Request| Response::text(200, "ok"))
+}
+
+fn preflight(origin: &str, method: &str, headers: &str) -> Request {
+    let mut req = Request::new("OPTIONS", "/api");
+    req.headers.insert("Origin", origin);
+    req.headers.insert("Access-Control-Request-Method", method);
+    if !headers.is_empty() {
+        req.headers
+            .insert("Access-Control-Request-Headers", headers);
+    }
+    req
+}
+
+#[test]
+fn answers_allowed_preflight() {
+    let response = api().handle(&preflight(
+        "https://app.example.com",
+        "PUT",
+        "content-type, x-token",
+    ));
+
+    assert_eq!(204, response.status);
+    let headers = &response.headers;
+    assert_eq!(
+        Some("https://app.example.com"),
+        headers.get("Access-Control-Allow-Origin")
+    );
+    assert_eq!(
+        Some("GET, PUT"),
+        headers.get("Access-Control-Allow-Methods")
+    );
+    assert_eq!(
+        Some("content-type, x-token"),
+        headers.get("Access-Control-Allow-Headers")
+    );
+    assert_eq!(
+        Some("true"),
+        headers.get("Access-Control-Allow-Credentials")
+    );
+    assert_eq!(Some("600"), headers.get("Access-Control-Max-Age"));
+    assert_eq!(Some("Origin"), headers.get("Vary"));
+}
+
+#[test]
+fn rejects_disallowed_preflight() {
+    let api = api();
+
+    let response = api.handle(&preflight("https://evil.example.com", "PUT", ""));
+    assert_eq!(403, response.status);
+    assert!(!response.headers.contains("Access-Control-Allow-Origin"));
+
+    let response = api.handle(&preflight("https://app.example.com", "DELETE", ""));
+    assert_eq!(403, response.status);
+
+    let response = api.handle(&preflight("https://app.example.com", "GET", "X-Other"));
+    assert_eq!(403, response.status);
+}
+
+#[test]
+fn adds_headers_to_cross_origin_requests() {
+    let api = api();
+
+    let mut req = Request::new("GET", "/api");
+    req.headers.insert("Origin", "https://app.example.com");
+    let response = api.handle(&req);
+    assert_eq!(200, response.status);
+    assert_eq!(
+        Some("https://app.example.com"),
+        response.headers.get("Access-Control-Allow-Origin")
+    );
+
+    // 没有 Origin 的请求原样通过
+    let response = api.handle(&Request::new("GET", "/api"));
+    assert!(!response.headers.contains("Access-Control-Allow-Origin"));
+
+    let any = Cors::new()
+        .allow_origin("*")
+        .wrap(|_: &Request| Response::text(200, "ok"));
+    req.headers.insert("Origin", "https://other.example.com");
+    let response = any.handle(&req);
+    assert_eq!(
+        Some("*"),
+        response.headers.get("Access-Control-Allow-Origin")
+    );
+    assert!(!response.headers.contains("Vary"));
+}
+
+#[test]
+#[should_panic(expected = "any origin")]
+fn rejects_credentials_for_any_origin() {
+    Cors::new()
+        .allow_origin("*")
+        .allow_credentials(true)
+        .wrap(|_: &Request| Response::text(200, "ok"));
+}
+
+#[test]
+fn security_headers_keep_handler_values() {
+    let handler = SecurityHeaders::new()
+        .hsts(Duration::from_secs(31536000), true)
+        .without("Referrer-Policy")
+        .wrap(|req: &Request| {
+            if req.path == "/embed" {
+                Response::text(200, "ok").with_header("X-Frame-Options", "SAMEORIGIN")
+            } else {
+                Response::text(200, "ok")
+            }
+        });
+
+    let response = handler.handle(&Request::new("GET", "/"));
+    let headers = &response.headers;
+    assert_eq!(
+        Some("default-src 'self'"),
+        headers.get("Content-Security-Policy")
+    );
+    assert_eq!(Some("nosniff"), headers.get("X-Content-Type-Options"));
+    assert_eq!(
+        Some("max-age=31536000; includeSubDomains"),
+        headers.get("Strict-Transport-Security")
+    );
+    assert!(!headers.contains("Referrer-Policy"));
+
+    let response = handler.handle(&Request::new("GET", "/embed"));
+    assert_eq!(Some("SAMEORIGIN"), response.headers.get("X-Frame-Options"));
+}
@@ -0,0 +1,135 @@
+use std::{fs, io};
+
+use chapt20_web_server::{
+    cookie::Cookie,
+    error::{ErrorPages, HttpError},
+    extract::Rejection,
+    request::Request,
+    response::Response,
+    server::Handler,
+};
+
+mod common;
+
+use common::{send_raw, spawn_server, temp_dir};
+
+fn body_text(response: Response) -> String {
+    String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+}
+
+fn failing(req: &Request) -> Result<Response, HttpError> {
+    match req.path.as_str() {
+        "/io" => Err(io::Error::new(io::ErrorKind::NotFound, "hello.html is missing").into()),
+        "/reject" => Err(Rejection::bad_request("name must not be <empty>").into()),
+        "/method" => Ok(Response::new(405).with_header("Allow", "GET")),
+        "/logout" => Ok(Response::new(401)
+            .with_header("Content-Type", "text/plain")
+            .with_cookie(&Cookie::new("a", "1"))
+            .with_cookie(&Cookie::new("b", "2"))),
+        "/panic" => panic!("boom"),
+        "/missing" => Err(HttpError::not_found()),
+        _ => Ok(Response::text(200, "ok")),
+    }
+}
+
+#[test]
+fn maps_errors_to_builtin_pages() {
+    let handler = ErrorPages::new().wrap(failing);
+
+    let response = handler.handle(&Request::new("GET", "/io"));
+    assert_eq!(500, response.status);
+    let body = body_text(response);
+    assert!(body.contains("<h1>500 Internal Server Error</h1>"));
+    // 服务器错误的原因只写日志
+    assert!(!body.contains("hello.html"));
+
+    let response = handler.handle(&Request::new("GET", "/reject"));
+    assert_eq!(400, response.status);
+    assert!(body_text(response).contains("name must not be &lt;empty&gt;"));
+
+    let response = handler.handle(&Request::new("GET", "/panic"));
+    assert_eq!(500, response.status);
+
+    let response = handler.handle(&Request::new("GET", "/"));
+    assert_eq!("ok", body_text(response));
+}
+
+#[test]
+fn uses_configured_pages_and_keeps_headers() {
+    let dir = temp_dir("error_pages");
+    fs::write(dir.join("404.html"), "custom not found").unwrap();
+    fs::write(dir.join("405.html"), "custom method").unwrap();
+
+    let handler = ErrorPages::new()
+        .page(404, dir.join("404.html"))
+        .page(405, dir.join("405.html"))
+        .page(500, dir.join("does-not-exist.html"))
+        .wrap(failing);
+
+    let response = handler.handle(&Request::new("GET", "/missing"));
+    assert_eq!(404, response.status);
+    assert_eq!("custom not found", body_text(response));
+
+    let response = handler.handle(&Request::new("GET", "/method"));
+    assert_eq!(405, response.status);
+    assert_eq!(Some("GET"), response.headers.get("Allow"));
+    assert_eq!(
+        Some("text/html; charset=utf-8"),
+        response.headers.get("Content-Type")
+    );
+    assert_eq!("custom method", body_text(response));
+
+    // 同名 header 都要保留
+    let response = handler.handle(&Request::new("GET", "/logout"));
+    assert_eq!(401, response.status);
+    assert_eq!(
+        vec!["a=1", "b=2"],
+        response.headers.get_all("Set-Cookie").collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["text/html; charset=utf-8"],
+        response.headers.get_all("Content-Type").collect::<Vec<_>>()
+    );
+
+    // 配置的页面读不到时退回内置页面
+    let response = handler.handle(&Request::new("GET", "/io"));
+    assert!(body_text(response).contains("500 Internal Server Error"));
+}
+
+#[test]
+fn server_survives_handler_panics() {
+    let addr = spawn_server(|req: &Request| {
+        if req.path == "/panic" {
+            panic!("boom");
+        }
+        Response::text(200, "ok")
+    });
+
+    // 线程池只有 2 个线程，panic 会杀死线程的话后面的请求就没有人处理
+    for _ in 0..3 {
+        let response = send_raw(addr, "GET /panic HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error"));
+    }
+    let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn server_survives_upgrade_panics() {
+    let addr = spawn_server(|req: &Request| {
+        if req.path == "/upgrade" {
+            return Response::new(101)
+                .with_header("Upgrade", "test")
+                .with_upgrade(|_| panic!("boom"));
+        }
+        Response::text(200, "ok")
+    });
+
+    // 升级的回调在 handle 之外运行，panic 同样不能让线程池失去线程
+    for _ in 0..3 {
+        let response = send_raw(addr, "GET /upgrade HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols"));
+    }
+    let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+}
@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    io::{self, Read},
+    os::unix::fs::MetadataExt,
+};
+
+use chapt20_web_server::{
+    extract::{Form, FromRequest, Json, Multipart, Query},
+    request::Request,
+    response::Response,
+};
+use serde::Deserialize;
+
+mod common;
+
+fn post(content_type: &str, body: &str) -> Request {
+    let mut req = Request::new("POST", "/submit?page=2&q=a+b%21");
+    req.headers.insert("Content-Type", content_type);
+    req.body = body.as_bytes().to_vec();
+    req
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Login {
+    user: String,
+    remember: bool,
+}
+
+#[test]
+fn query_and_form() {
+    let req = post(
+        "application/x-www-form-urlencoded; charset=utf-8",
+        "user=ferris&msg=hello+world%21&empty=",
+    );
+
+    let query = Query::from_request(&req).unwrap();
+    assert_eq!(Some("2"), query.get("page"));
+    assert_eq!(Some("a b!"), query.get("q"));
+
+    let form = Form::from_request(&req).unwrap();
+    assert_eq!(Some("ferris"), form.get("user"));
+    assert_eq!(Some("hello world!"), form.get("msg"));
+    assert_eq!(Some(""), form.get("empty"));
+
+    assert_eq!(
+        413,
+        Form::from_request_with_limit(&req, 4).unwrap_err().status
+    );
+}
+
+#[test]
+fn json_body() {
+    let req = post(
+        "application/json",
+        r#"{"user": "ferris", "remember": true}"#,
+    );
+    let Json(login) = Json::<Login>::from_request(&req).unwrap();
+    assert_eq!(
+        Login {
+            user: "ferris".to_string(),
+            remember: true
+        },
+        login
+    );
+
+    let req = post("application/json", r#"{"user": "ferris"}"#);
+    assert_eq!(400, Json::<Login>::from_request(&req).unwrap_err().status);
+
+    let req = post("text/plain", "{}");
+    assert_eq!(415, Json::<Login>::from_request(&req).unwrap_err().status);
+}
+
+#[test]
+fn multipart_with_file() {
+    let body = "--XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+        My upload\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+        Content-Type: text/plain\r\n\r\n\
+        line one\r\nline two\r\n\
+        --XyZ--\r\n";
+    let req = post("multipart/form-data; boundary=XyZ", body);
+
+    let multipart = Multipart::from_request(&req).unwrap();
+    assert_eq!(Some("My upload"), multipart.field("title"));
+
+    let file = multipart.file("file").unwrap();
+    assert_eq!("a.txt", file.filename);
+    assert_eq!(Some("text/plain"), file.content_type.as_deref());
+    assert_eq!(
+        "line one\r\nline two",
+        fs::read_to_string(file.path()).unwrap()
+    );
+
+    // 临时文件随 FilePart 一起删除
+    let path = file.path().clone();
+    drop(multipart);
+    assert!(!path.exists());
+
+    let req = post("multipart/form-data; boundary=XyZ", "garbage");
+    assert_eq!(400, Multipart::from_request(&req).unwrap_err().status);
+}
+
+#[test]
+fn server_reads_bodies() {
+    let addr = common::spawn_server(|req: &Request| match Form::from_request(req) {
+        Ok(form) => Response::text(200, form.get("name").unwrap_or("").to_string()),
+        Err(rejection) => rejection.into_response(),
+    });
+
+    let response = common::send_raw(
+        addr,
+        "POST / HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n\
+         Content-Length: 9\r\n\r\nname=Ann!",
+    );
+    assert!(response.ends_with("\r\n\r\nAnn!"));
+
+    let response = common::send_raw(
+        addr,
+        "POST / HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n\
+         Transfer-Encoding: chunked\r\n\r\n4\r\nname\r\n4\r\n=Bob\r\n0\r\n\r\n",
+    );
+    assert!(response.ends_with("\r\n\r\nBob"));
+
+    let response = common::send_raw(addr, "POST / HTTP/1.1\r\nContent-Length: 1\r\n\r\nx");
+    assert!(response.starts_with("HTTP/1.1 415 "));
+}
+
+// 每次只返回几个字节，分隔符会被拆在两次读取之间
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(3);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn multipart_streams_from_reader() {
+    let body = "preamble\r\n--XyZ\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r\n\r\n\
+        --Xy\r\n--XyQ\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"note\"\r\n\r\n\
+        hi\r\n\
+        --XyZ--\r\n";
+    // 流式读取时 req.body 为空
+    let req = post("multipart/form-data; boundary=XyZ", "");
+
+    let multipart = Multipart::from_reader(&req, &mut Trickle(body.as_bytes()), 1024).unwrap();
+    assert_eq!(Some("hi"), multipart.field("note"));
+    let file = multipart.file("file").unwrap();
+    assert_eq!(11, file.size);
+    assert_eq!("--Xy\r\n--XyQ", fs::read_to_string(file.path()).unwrap());
+
+    // 临时文件名不可预测，只有当前用户可以读写
+    let name = file.path().file_name().unwrap().to_str().unwrap();
+    assert!(!name.contains(&std::process::id().to_string()));
+    assert_eq!(0o600, fs::metadata(file.path()).unwrap().mode() & 0o777);
+
+    let err = Multipart::from_reader(&req, &mut Trickle(body.as_bytes()), 64).unwrap_err();
+    assert_eq!(413, err.status);
+}
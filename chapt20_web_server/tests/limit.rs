@@ -0,0 +1,98 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chapt20_web_server::{limit::Limiter, request::Request, response::Response, server::Server};
+
+mod common;
+
+use common::send_raw;
+
+fn spawn_limited(limiter: Arc<Limiter>) -> SocketAddr {
+    let server = Server::bind("127.0.0.1:0", 2, |_: &Request| Response::text(200, "ok"))
+        .unwrap()
+        .limiter(limiter);
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+#[test]
+fn rejects_connections_over_per_ip_limit() {
+    let limiter = Arc::new(Limiter::new().max_connections_per_ip(1));
+    let addr = spawn_limited(Arc::clone(&limiter));
+
+    // 一直不发送请求的连接占住名额
+    let idle = TcpStream::connect(addr).unwrap();
+    while limiter.stats().active_connections == 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 429 Too Many Requests"));
+    assert!(response.contains("Retry-After: 1\r\n"));
+    assert_eq!(1, limiter.stats().rejected_connections);
+
+    drop(idle);
+    while limiter.stats().active_connections > 0 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn rate_limits_requests() {
+    let limiter = Arc::new(Limiter::new().rate_limit(0.5, 2));
+    let addr = spawn_limited(Arc::clone(&limiter));
+
+    for _ in 0..2 {
+        let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+    let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 429 Too Many Requests"));
+    assert!(response.contains("Retry-After: 2\r\n"));
+
+    let stats = limiter.stats();
+    assert_eq!(1, stats.rate_limited_requests);
+    assert_eq!(3, stats.accepted_connections);
+}
+
+#[test]
+fn times_out_slow_and_idle_connections() {
+    let server = Server::bind("127.0.0.1:0", 1, |_: &Request| Response::text(200, "ok"))
+        .unwrap()
+        .header_timeout(Duration::from_millis(200));
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    // 一直不发送请求的连接在超时后被直接关闭
+    let mut idle = TcpStream::connect(addr).unwrap();
+    let mut response = String::new();
+    idle.read_to_string(&mut response).unwrap();
+    assert_eq!("", response);
+
+    // 请求头一点点发送，每次读取都不超时，但总时间超过了上限
+    let start = Instant::now();
+    let mut slow = TcpStream::connect(addr).unwrap();
+    slow.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+    for _ in 0..4 {
+        thread::sleep(Duration::from_millis(100));
+        if slow.write_all(b"X-Slow: 1\r\n").is_err() {
+            break;
+        }
+    }
+    let mut response = String::new();
+    let _ = slow.read_to_string(&mut response);
+    assert!(response.starts_with("HTTP/1.1 408 Request Timeout"));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    // 只有一个工作线程，上面的连接没有一直占着它
+    let response = send_raw(addr, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+}
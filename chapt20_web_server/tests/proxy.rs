@@ -0,0 +1,238 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener},
+    thread,
+    time::Duration,
+};
+
+use chapt20_web_server::{
+    cors::Cors, proxy::Proxy, request::Request, response::Response, security::SecurityHeaders,
+};
+
+mod common;
+
+use common::{send_raw, spawn_server};
+
+// 上游把收到的请求原样描述出来
+fn spawn_upstream(name: &'static str) -> SocketAddr {
+    spawn_server(move |req: &Request| {
+        let target = match &req.query {
+            Some(query) => format!("{}?{}", req.path, query),
+            None => req.path.clone(),
+        };
+        Response::text(
+            200,
+            format!(
+                "{} {} {}\nhost={}\nxff={}\nxfh={}\nconnection={}\nbody={}\n",
+                name,
+                req.method,
+                target,
+                req.header("Host").unwrap_or("-"),
+                req.header("X-Forwarded-For").unwrap_or("-"),
+                req.header("X-Forwarded-Host").unwrap_or("-"),
+                req.header("Connection").unwrap_or("-"),
+                String::from_utf8_lossy(&req.body),
+            ),
+        )
+        .with_header("X-Upstream", name)
+    })
+}
+
+// 一个没有服务在监听的地址
+fn dead_addr() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+fn body(response: &str) -> &str {
+    response.split_once("\r\n\r\n").unwrap().1
+}
+
+#[test]
+fn forwards_request_and_rewrites_headers() {
+    let upstream = spawn_upstream("a").to_string();
+    let proxy = spawn_server(Proxy::new("/api", &[&upstream]).strip_prefix(true));
+
+    let response = send_raw(
+        proxy,
+        "POST /api/items?x=1 HTTP/1.1\r\nHost: example.com\r\n\
+         X-Forwarded-For: 10.0.0.1\r\nContent-Length: 5\r\n\r\nhello",
+    );
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("X-Upstream: a\r\n"));
+    let body = body(&response);
+    assert!(body.starts_with("a POST /items?x=1\n"));
+    assert!(body.contains(&format!("host={}\n", upstream)));
+    assert!(body.contains("xff=10.0.0.1, 127.0.0.1\n"));
+    assert!(body.contains("xfh=example.com\n"));
+    assert!(body.contains("connection=close\n"));
+    assert!(body.contains("body=hello\n"));
+}
+
+#[test]
+fn round_robins_across_upstreams() {
+    let a = spawn_upstream("a").to_string();
+    let b = spawn_upstream("b").to_string();
+    let proxy = spawn_server(Proxy::new("/", &[&a, &b]));
+
+    let names: Vec<String> = (0..4)
+        .map(|_| {
+            let response = send_raw(proxy, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+            body(&response)[..1].to_string()
+        })
+        .collect();
+
+    assert_eq!(vec!["a", "b", "a", "b"], names);
+}
+
+#[test]
+fn skips_failed_upstream() {
+    let dead = dead_addr().to_string();
+    let live = spawn_upstream("live").to_string();
+    let proxy = spawn_server(
+        Proxy::new("/", &[&dead, &live])
+            .connect_timeout(Duration::from_secs(1))
+            .health(1, Duration::from_secs(60)),
+    );
+
+    // 每个请求都成功，死掉的上游第一次失败后就不再被选中
+    for _ in 0..3 {
+        let response = send_raw(proxy, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(body(&response).starts_with("live GET /"));
+    }
+}
+
+#[test]
+fn returns_bad_gateway_without_upstreams() {
+    let dead = dead_addr().to_string();
+    let proxy = spawn_server(Proxy::new("/", &[&dead]));
+
+    let response = send_raw(proxy, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 502 Bad Gateway"));
+}
+
+#[test]
+fn skips_interim_responses() {
+    // 上游先回复 100 Continue，再回复最终的响应
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let upstream = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        stream
+            .write_all(
+                b"HTTP/1.1 100 Continue\r\n\r\n\
+                  HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ndone",
+            )
+            .unwrap();
+    });
+    let proxy = spawn_server(Proxy::new("/", &[&upstream]));
+
+    let response = send_raw(proxy, "GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert_eq!("done", body(&response));
+}
+
+#[test]
+fn streams_chunked_request_body() {
+    let upstream = spawn_upstream("a").to_string();
+    let proxy = spawn_server(Proxy::new("/", &[&upstream]));
+
+    let response = send_raw(
+        proxy,
+        "POST /upload HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+         5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+    );
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(body(&response).contains("body=hello world\n"));
+}
+
+#[test]
+fn wrapped_proxy_still_streams() {
+    // 缓冲后转发会带 Content-Length，流式转发保持 chunked
+    let upstream = spawn_server(|req: &Request| {
+        Response::text(
+            200,
+            format!(
+                "te={} body={}",
+                req.header("Transfer-Encoding").unwrap_or("-"),
+                String::from_utf8_lossy(&req.body)
+            ),
+        )
+    })
+    .to_string();
+    let proxy = spawn_server(
+        SecurityHeaders::new().wrap(
+            Cors::new()
+                .allow_origin("https://app.example.com")
+                .wrap(Proxy::new("/", &[&upstream])),
+        ),
+    );
+
+    let response = send_raw(
+        proxy,
+        "POST / HTTP/1.1\r\nHost: x\r\nOrigin: https://app.example.com\r\n\
+         Transfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+    );
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("X-Frame-Options: DENY\r\n"));
+    assert!(response.contains("Access-Control-Allow-Origin: https://app.example.com\r\n"));
+    assert!(response.ends_with("te=chunked body=hello"));
+}
+
+#[test]
+fn rejects_paths_outside_prefix() {
+    let upstream = spawn_upstream("a").to_string();
+    let proxy = spawn_server(Proxy::new("/legacy", &[&upstream]).strip_prefix(true));
+
+    // 比前缀短的路径不能 panic
+    for path in ["/", "/l\u{e9}gacy"] {
+        let response = send_raw(proxy, &format!("GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path));
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"), "{}", path);
+    }
+}
+
+#[test]
+fn does_not_forward_expect() {
+    let upstream = spawn_server(|req: &Request| {
+        let expect = req.header("Expect").unwrap_or("-");
+        Response::text(
+            200,
+            format!(
+                "expect={} body={}",
+                expect,
+                String::from_utf8_lossy(&req.body)
+            ),
+        )
+    })
+    .to_string();
+    let proxy = spawn_server(Proxy::new("/", &[&upstream]));
+
+    let response = send_raw(
+        proxy,
+        "PUT / HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nContent-Length: 2\r\n\r\nhi",
+    );
+
+    // 代理自己回复 100 Continue，上游的响应不应该再包含一次
+    let (interim, response) = response.split_once("\r\n\r\n").unwrap();
+    assert_eq!("HTTP/1.1 100 Continue", interim);
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("expect=- body=hi"));
+}
+
+#[test]
+fn matches_prefix_on_segment_boundary() {
+    let proxy = Proxy::new("/api/", &["127.0.0.1:1"]);
+
+    assert!(proxy.matches(&Request::new("GET", "/api")));
+    assert!(proxy.matches(&Request::new("GET", "/api/users")));
+    assert!(!proxy.matches(&Request::new("GET", "/apix")));
+}
@@ -0,0 +1,111 @@
+use std::{thread, time::Duration};
+
+use chapt20_web_server::{
+    request::Request,
+    response::Response,
+    server::Handler,
+    session::{FileStore, MemoryStore, SessionStore, Sessions},
+};
+
+mod common;
+
+const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+// 取出 Set-Cookie 中的 name=value 部分
+fn session_cookie(response: &Response) -> Option<String> {
+    response
+        .headers
+        .get("Set-Cookie")
+        .map(|c| c.split(';').next().unwrap().to_string())
+}
+
+fn request_with_cookie(cookie: Option<&str>) -> Request {
+    let mut req = Request::new("GET", "/");
+    if let Some(cookie) = cookie {
+        req.headers.insert("Cookie", cookie);
+    }
+    req
+}
+
+fn counter(store: impl SessionStore) -> impl Handler {
+    Sessions::new(store, SECRET)
+        .ttl(Duration::from_secs(1))
+        .wrap(|req, session| {
+            if req.path == "/logout" {
+                session.destroy();
+                return Response::new(204);
+            }
+            let count: u32 = session.get("n").and_then(|n| n.parse().ok()).unwrap_or(0);
+            session.insert("n", (count + 1).to_string());
+            Response::text(200, (count + 1).to_string())
+        })
+}
+
+fn body(response: Response) -> String {
+    String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+}
+
+#[test]
+fn session_round_trip() {
+    let handler = counter(MemoryStore::new());
+
+    let first = handler.handle(&request_with_cookie(None));
+    let cookie = session_cookie(&first).unwrap();
+    assert!(first
+        .headers
+        .get("Set-Cookie")
+        .unwrap()
+        .contains("; HttpOnly; SameSite=Lax"));
+    assert_eq!("1", body(first));
+
+    let second = handler.handle(&request_with_cookie(Some(&cookie)));
+    assert_eq!("2", body(second));
+
+    let mut logout = request_with_cookie(Some(&cookie));
+    logout.path = "/logout".to_string();
+    let response = handler.handle(&logout);
+    assert!(response
+        .headers
+        .get("Set-Cookie")
+        .unwrap()
+        .contains("Max-Age=0"));
+
+    let after = handler.handle(&request_with_cookie(Some(&cookie)));
+    assert_eq!("1", body(after));
+}
+
+#[test]
+fn rejects_tampered_cookie() {
+    let handler = counter(MemoryStore::new());
+    let cookie = session_cookie(&handler.handle(&request_with_cookie(None))).unwrap();
+
+    let tampered = format!("{}0", cookie);
+    assert_eq!(
+        "1",
+        body(handler.handle(&request_with_cookie(Some(&tampered))))
+    );
+    let forged = cookie.replacen('=', "=f", 1);
+    assert_eq!(
+        "1",
+        body(handler.handle(&request_with_cookie(Some(&forged))))
+    );
+}
+
+#[test]
+fn file_store_expires_sessions() {
+    let dir = common::temp_dir("sessions");
+    let handler = counter(FileStore::new(&dir).unwrap());
+
+    let cookie = session_cookie(&handler.handle(&request_with_cookie(None))).unwrap();
+    assert_eq!(1, dir.read_dir().unwrap().count());
+    assert_eq!(
+        "2",
+        body(handler.handle(&request_with_cookie(Some(&cookie))))
+    );
+
+    thread::sleep(Duration::from_millis(2100));
+    assert_eq!(
+        "1",
+        body(handler.handle(&request_with_cookie(Some(&cookie))))
+    );
+}
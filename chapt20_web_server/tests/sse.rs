@@ -0,0 +1,136 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chapt20_web_server::{
+    request::Request,
+    server::handle_connection,
+    sse::{self, Event, EventStream},
+    ThreadPool,
+};
+
+mod common;
+
+use common::spawn_server;
+
+// 读到空行为止，返回一个完整的事件帧（或响应头）
+fn read_frame(reader: &mut impl BufRead) -> String {
+    let mut frame = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap() == 0 {
+            return frame;
+        }
+        if line.trim_end_matches(['\r', '\n']).is_empty() {
+            return frame;
+        }
+        frame.push_str(&line);
+    }
+}
+
+fn open(addr: SocketAddr, path: &str) -> BufReader<TcpStream> {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path).unwrap();
+    BufReader::new(stream)
+}
+
+#[test]
+fn streams_events_and_heartbeats() {
+    let addr = spawn_server(|req: &Request| {
+        let (sender, response) = EventStream::new()
+            .heartbeat(Some(Duration::from_millis(200)))
+            .retry(Duration::from_secs(2))
+            .open();
+        let last = sse::last_event_id(req).unwrap_or("0").to_string();
+        thread::spawn(move || {
+            sender
+                .send(&Event::new("hello\nworld").id(last).event("greeting"))
+                .unwrap();
+            // 等待一次心跳后结束
+            thread::sleep(Duration::from_millis(500));
+            let _ = sender.send(&Event::new("bye"));
+        });
+        response
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream
+        .write_all(b"GET /events HTTP/1.1\r\nHost: x\r\nLast-Event-ID: 41\r\n\r\n")
+        .unwrap();
+    let mut reader = BufReader::new(stream);
+
+    let head = read_frame(&mut reader);
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    assert!(head.contains("Content-Type: text/event-stream\r\n"));
+    assert!(head.contains("Cache-Control: no-cache\r\n"));
+    assert!(!head.contains("Content-Length"));
+
+    assert_eq!("retry: 2000\n", read_frame(&mut reader));
+    assert_eq!(
+        "id: 41\nevent: greeting\ndata: hello\ndata: world\n",
+        read_frame(&mut reader)
+    );
+    // 空闲期间至少有一次心跳
+    let mut heartbeats = 0;
+    let last = loop {
+        match read_frame(&mut reader) {
+            frame if frame == ": heartbeat\n" => heartbeats += 1,
+            frame => break frame,
+        }
+    };
+    assert!(heartbeats >= 1);
+    assert_eq!("data: bye\n", last);
+
+    // 发送端丢弃后服务器关闭连接
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn pool_shutdown_closes_streams() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (senders_tx, senders_rx) = mpsc::channel();
+
+    let pool = ThreadPool::new(1);
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        pool.execute(move || {
+            let handler = move |_: &Request| {
+                let (sender, response) = sse::channel();
+                // 发送端一直不丢弃，只有线程池关闭才能结束这个流
+                senders_tx.send(sender).unwrap();
+                response
+            };
+            let _ = handle_connection(stream, &handler, 1024, Duration::from_secs(5), None, None);
+        });
+        pool
+    });
+
+    let mut reader = open(addr, "/events");
+    assert!(read_frame(&mut reader).starts_with("HTTP/1.1 200 OK"));
+    let sender = senders_rx.recv().unwrap();
+    sender.send(&Event::new("first")).unwrap();
+    assert_eq!("data: first\n", read_frame(&mut reader));
+
+    let pool = server.join().unwrap();
+    let start = Instant::now();
+    drop(pool);
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+    assert!(sender.send(&Event::new("late")).is_err());
+}
@@ -0,0 +1,157 @@
+use std::fs;
+
+use chapt20_web_server::{request::Request, static_files::StaticFiles};
+
+mod common;
+
+fn get(path: &str, headers: &[(&str, &str)]) -> Request {
+    let mut req = Request::new("GET", path);
+    for (name, value) in headers {
+        req.headers.insert(name, *value);
+    }
+    req
+}
+
+#[test]
+fn conditional_requests() {
+    let root = common::temp_dir("conditional");
+    fs::write(root.join("index.html"), "<h1>Hello!</h1>").unwrap();
+    let files = StaticFiles::new(&root).cache_control("public, max-age=60");
+
+    let response = files.serve(&get("/", &[]));
+    assert_eq!(200, response.status);
+    assert_eq!(
+        Some("public, max-age=60"),
+        response.headers.get("Cache-Control")
+    );
+    let etag = response.headers.get("ETag").unwrap().to_string();
+    let last_modified = response.headers.get("Last-Modified").unwrap().to_string();
+
+    let response = files.serve(&get("/index.html", &[("If-None-Match", &etag)]));
+    assert_eq!(304, response.status);
+    assert!(response.body.is_empty());
+
+    let response = files.serve(&get("/", &[("If-Modified-Since", &last_modified)]));
+    assert_eq!(304, response.status);
+
+    let response = files.serve(&get("/", &[("If-None-Match", "\"other\"")]));
+    assert_eq!(200, response.status);
+}
+
+#[test]
+fn cache_is_invalidated_on_change() {
+    let root = common::temp_dir("cache");
+    let path = root.join("data.txt");
+    fs::write(&path, "old").unwrap();
+    let files = StaticFiles::new(&root).with_cache(1024);
+
+    let response = files.serve(&get("/data.txt", &[]));
+    assert_eq!(
+        Some("text/plain; charset=utf-8"),
+        response.headers.get("Content-Type")
+    );
+    assert_eq!(b"old".to_vec(), response.body.into_bytes().unwrap());
+
+    fs::write(&path, "newer").unwrap();
+    let response = files.serve(&get("/data.txt", &[]));
+    assert_eq!(b"newer".to_vec(), response.body.into_bytes().unwrap());
+}
+
+#[test]
+fn range_requests() {
+    let root = common::temp_dir("range");
+    fs::write(root.join("data.bin"), "0123456789").unwrap();
+
+    // 分别测试从磁盘读取和从缓存读取
+    for files in [
+        StaticFiles::new(&root),
+        StaticFiles::new(&root).with_cache(1024),
+    ] {
+        let response = files.serve(&get("/data.bin", &[("Range", "bytes=2-4")]));
+        assert_eq!(206, response.status);
+        assert_eq!(Some("bytes 2-4/10"), response.headers.get("Content-Range"));
+        assert_eq!(b"234".to_vec(), response.body.into_bytes().unwrap());
+
+        let response = files.serve(&get("/data.bin", &[("Range", "bytes=-3")]));
+        assert_eq!(b"789".to_vec(), response.body.into_bytes().unwrap());
+
+        let response = files.serve(&get("/data.bin", &[("Range", "bytes=20-")]));
+        assert_eq!(416, response.status);
+        assert_eq!(Some("bytes */10"), response.headers.get("Content-Range"));
+
+        let response = files.serve(&get("/data.bin", &[("Range", "bytes=9-0")]));
+        assert_eq!(200, response.status);
+        assert_eq!(Some("bytes"), response.headers.get("Accept-Ranges"));
+
+        // 没有区间的 Range 按规范忽略，返回完整内容
+        let response = files.serve(&get("/data.bin", &[("Range", "bytes=")]));
+        assert_eq!(200, response.status);
+        assert_eq!(b"0123456789".to_vec(), response.body.into_bytes().unwrap());
+    }
+}
+
+#[test]
+fn multi_range_request() {
+    let root = common::temp_dir("multirange");
+    fs::write(root.join("data.txt"), "0123456789").unwrap();
+    let files = StaticFiles::new(&root);
+
+    let response = files.serve(&get("/data.txt", &[("Range", "bytes=0-1,8-")]));
+    assert_eq!(206, response.status);
+    let content_type = response.headers.get("Content-Type").unwrap().to_string();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap();
+
+    let len = response.body.len().unwrap();
+    let body = String::from_utf8(response.body.into_bytes().unwrap()).unwrap();
+    assert_eq!(len, body.len() as u64);
+    assert_eq!(
+        format!(
+            "\r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+             \r\n--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 8-9/10\r\n\r\n89\
+             \r\n--{b}--\r\n",
+            b = boundary
+        ),
+        body
+    );
+}
+
+#[test]
+fn if_range_mismatch_returns_full_content() {
+    let root = common::temp_dir("ifrange");
+    fs::write(root.join("data.txt"), "0123456789").unwrap();
+    let files = StaticFiles::new(&root);
+
+    let req = get(
+        "/data.txt",
+        &[("Range", "bytes=0-1"), ("If-Range", "\"stale\"")],
+    );
+    assert_eq!(200, files.serve(&req).status);
+}
+
+#[test]
+fn rejects_paths_outside_root() {
+    let root = common::temp_dir("traversal");
+    let files = StaticFiles::new(root.join("public"));
+    fs::create_dir(root.join("public")).unwrap();
+    fs::write(root.join("secret.txt"), "secret").unwrap();
+
+    assert_eq!(404, files.serve(&get("/../secret.txt", &[])).status);
+    assert_eq!(404, files.serve(&get("/%2e%2e/secret.txt", &[])).status);
+}
+
+#[test]
+fn serves_over_tcp() {
+    let root = common::temp_dir("tcp");
+    fs::write(root.join("index.html"), "hi").unwrap();
+    let addr = common::spawn_server(StaticFiles::new(&root));
+
+    let response = common::send_raw(addr, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nhi"));
+
+    let response = common::send_raw(addr, "HEAD / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.contains("Content-Length: 2\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+}
@@ -0,0 +1,97 @@
+use std::{fs, thread, time::Duration};
+
+use chapt20_web_server::template::{Context, Templates};
+
+mod common;
+
+#[test]
+fn renders_variables_and_blocks() {
+    let dir = common::temp_dir("template_blocks");
+    fs::write(
+        dir.join("list.html"),
+        "<h1>{{ title }}</h1>{% if not items %}empty{% endif %}\
+         {% for item in items %}{{ loop.index }}:{{ item.name }}{% if not loop.last %},{% endif %}{% endfor %}\
+         {{ html | raw }}",
+    )
+    .unwrap();
+    let templates = Templates::new(&dir);
+
+    let items = vec![
+        Context::new().with("name", "<b>"),
+        Context::new().with("name", "ok"),
+    ];
+    let context = Context::new()
+        .with("title", "Tom & Jerry")
+        .with("items", items)
+        .with("html", "<br>");
+
+    assert_eq!(
+        "<h1>Tom &amp; Jerry</h1>1:&lt;b&gt;,2:ok<br>",
+        templates.render("list.html", &context).unwrap()
+    );
+    assert_eq!(
+        "<h1></h1>empty",
+        templates.render("list.html", &Context::new()).unwrap()
+    );
+}
+
+#[test]
+fn layouts_and_includes() {
+    let dir = common::temp_dir("template_layout");
+    fs::write(
+        dir.join("layout.html"),
+        "<title>{% block title %}Default{% endblock %}</title>{% include \"nav.html\" %}{% block body %}{% endblock %}",
+    )
+    .unwrap();
+    fs::write(dir.join("nav.html"), "<nav>{{ user }}</nav>").unwrap();
+    fs::write(
+        dir.join("page.html"),
+        "{% extends \"layout.html\" %}{% block body %}<p>{{ path }}</p>{% endblock %}",
+    )
+    .unwrap();
+    let templates = Templates::new(&dir);
+
+    let context = Context::new()
+        .with("user", "ferris")
+        .with("path", "/missing");
+    assert_eq!(
+        "<title>Default</title><nav>ferris</nav><p>/missing</p>",
+        templates.render("page.html", &context).unwrap()
+    );
+}
+
+#[test]
+fn reports_errors() {
+    let dir = common::temp_dir("template_errors");
+    fs::write(dir.join("bad.html"), "{% if x %}never closed").unwrap();
+    fs::write(dir.join("loop.html"), "{% include \"loop.html\" %}").unwrap();
+    let templates = Templates::new(&dir);
+
+    let err = templates.render("bad.html", &Context::new()).unwrap_err();
+    assert_eq!("template bad.html: missing {% endif %}", err.to_string());
+    assert!(templates.render("loop.html", &Context::new()).is_err());
+    assert!(templates.render("../secret", &Context::new()).is_err());
+}
+
+#[test]
+fn hot_reload() {
+    let dir = common::temp_dir("template_reload");
+    let path = dir.join("page.html");
+    fs::write(&path, "v1").unwrap();
+    let cached = Templates::new(&dir);
+    let reloading = Templates::new(&dir).hot_reload(true);
+    assert_eq!("v1", cached.render("page.html", &Context::new()).unwrap());
+    assert_eq!(
+        "v1",
+        reloading.render("page.html", &Context::new()).unwrap()
+    );
+
+    // 保证修改时间发生变化
+    thread::sleep(Duration::from_millis(20));
+    fs::write(&path, "v2").unwrap();
+    assert_eq!("v1", cached.render("page.html", &Context::new()).unwrap());
+    assert_eq!(
+        "v2",
+        reloading.render("page.html", &Context::new()).unwrap()
+    );
+}
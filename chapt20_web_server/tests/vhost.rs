@@ -0,0 +1,84 @@
+use std::fs;
+
+use chapt20_web_server::{
+    request::Request, response::Response, server::Handler, vhost::VirtualHosts,
+};
+
+mod common;
+
+use common::temp_dir;
+
+fn site(name: &'static str) -> impl Handler {
+    move |_: &Request| Response::text(200, name)
+}
+
+fn body_for(hosts: &VirtualHosts, host: Option<&str>) -> (u16, String) {
+    let mut req = Request::new("GET", "/");
+    if let Some(host) = host {
+        req.headers.insert("Host", host);
+    }
+    let response = hosts.handle(&req);
+    let body = response.body.into_bytes().unwrap();
+    (response.status, String::from_utf8(body).unwrap())
+}
+
+#[test]
+fn routes_by_host_header() {
+    let hosts = VirtualHosts::new()
+        .host("example.com", site("main"))
+        .host("*.example.com", site("any-sub"))
+        .host("*.api.example.com", site("api-sub"))
+        .host("api.example.com", site("api"));
+
+    let cases = [
+        ("example.com", "main"),
+        ("EXAMPLE.com:8080", "main"),
+        ("example.com.", "main"),
+        ("www.example.com", "any-sub"),
+        ("a.b.example.com", "any-sub"),
+        ("api.example.com", "api"),
+        ("v1.api.example.com", "api-sub"),
+    ];
+    for (host, expected) in cases {
+        assert_eq!(
+            (200, expected.to_string()),
+            body_for(&hosts, Some(host)),
+            "{}",
+            host
+        );
+    }
+
+    let (status, _) = body_for(&hosts, Some("other.org"));
+    assert_eq!(404, status);
+}
+
+#[test]
+fn falls_back_to_default_host() {
+    let hosts = VirtualHosts::new()
+        .host("example.com", site("main"))
+        .default_host(site("default"));
+
+    assert_eq!(
+        (200, "default".to_string()),
+        body_for(&hosts, Some("other.org"))
+    );
+    assert_eq!((200, "default".to_string()), body_for(&hosts, None));
+    assert_eq!(
+        (200, "default".to_string()),
+        body_for(&hosts, Some("[::1]:7878"))
+    );
+}
+
+#[test]
+fn serves_document_root_per_host() {
+    let dir = temp_dir("vhost");
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs/index.html"), "docs site").unwrap();
+
+    let hosts = VirtualHosts::new().root("docs.internal", dir.join("docs"));
+
+    assert_eq!(
+        (200, "docs site".to_string()),
+        body_for(&hosts, Some("docs.internal:7878"))
+    );
+}
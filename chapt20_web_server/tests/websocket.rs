@@ -0,0 +1,124 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::{mpsc, Mutex},
+    time::Duration,
+};
+
+use chapt20_web_server::{
+    request::Request,
+    websocket::{
+        self, Frame, Message, OpCode, Role, WebSocket, CLOSE_GOING_AWAY, CLOSE_PROTOCOL_ERROR,
+        IDLE_TIMEOUT,
+    },
+};
+
+mod common;
+
+const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+// 测试用的 WebSocket 客户端：发送握手请求并检查 101 响应
+fn connect(addr: SocketAddr, version: &str) -> Result<WebSocket<TcpStream>, String> {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let request = format!(
+        "GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+         Connection: keep-alive, Upgrade\r\nSec-WebSocket-Key: {}\r\n\
+         Sec-WebSocket-Version: {}\r\n\r\n",
+        KEY, version
+    );
+    stream.write_all(request.as_bytes()).unwrap();
+
+    // 逐字节读取响应头，避免读走后面的帧
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).unwrap();
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8(head).unwrap();
+
+    if !head.starts_with("HTTP/1.1 101 ") {
+        return Err(head);
+    }
+    assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    Ok(WebSocket::new(stream, Role::Client))
+}
+
+fn echo_server() -> SocketAddr {
+    common::spawn_server(|req: &Request| websocket::upgrade(req, websocket::echo))
+}
+
+#[test]
+fn echoes_messages() {
+    let mut ws = connect(echo_server(), "13").unwrap();
+
+    ws.send(Message::Text("hello".to_string())).unwrap();
+    assert_eq!(Message::Text("hello".to_string()), ws.recv().unwrap());
+
+    let big = vec![42; 70_000];
+    ws.send(Message::Binary(big.clone())).unwrap();
+    assert_eq!(Message::Binary(big), ws.recv().unwrap());
+}
+
+#[test]
+fn reassembles_fragments_around_ping() {
+    let mut ws = connect(echo_server(), "13").unwrap();
+
+    let mut first = Frame::new(OpCode::Text, "Hel");
+    first.fin = false;
+    ws.send_frame(first).unwrap();
+    ws.send_frame(Frame::new(OpCode::Ping, "are you there"))
+        .unwrap();
+    ws.send_frame(Frame::new(OpCode::Continuation, "lo"))
+        .unwrap();
+
+    assert_eq!(Message::Pong(b"are you there".to_vec()), ws.recv().unwrap());
+    assert_eq!(Message::Text("Hello".to_string()), ws.recv().unwrap());
+}
+
+#[test]
+fn close_handshake() {
+    let mut ws = connect(echo_server(), "13").unwrap();
+
+    ws.close(CLOSE_GOING_AWAY, "bye").unwrap();
+    assert_eq!(
+        Message::Close(Some((CLOSE_GOING_AWAY, String::new()))),
+        ws.recv().unwrap()
+    );
+    assert!(ws.is_closed());
+}
+
+#[test]
+fn rejects_one_byte_close_payload() {
+    let mut ws = connect(echo_server(), "13").unwrap();
+
+    ws.send_frame(Frame::new(OpCode::Close, vec![3])).unwrap();
+    // 服务器回复 Close 后就断开，直接读帧，不再回复
+    let frame = Frame::read_from(&mut ws.get_ref(), 1024).unwrap();
+    assert_eq!(OpCode::Close, frame.opcode);
+    assert_eq!(CLOSE_PROTOCOL_ERROR.to_be_bytes(), frame.payload[..]);
+}
+
+#[test]
+fn sets_idle_timeout() {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    let addr = common::spawn_server(move |req: &Request| {
+        let tx = tx.lock().unwrap().clone();
+        websocket::upgrade(req, move |ws| {
+            tx.send(ws.get_ref().stream().read_timeout().unwrap())
+                .unwrap();
+        })
+    });
+
+    let _ws = connect(addr, "13").unwrap();
+    let timeout = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(Some(IDLE_TIMEOUT), timeout);
+}
+
+#[test]
+fn rejects_unsupported_version() {
+    let head = connect(echo_server(), "8").err().unwrap();
+    assert!(head.starts_with("HTTP/1.1 426 "));
+    assert!(head.contains("Sec-WebSocket-Version: 13\r\n"));
+}